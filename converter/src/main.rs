@@ -1,92 +1,166 @@
-use regex::Regex;
-use rusqlite::Connection;
 use std::path::Path;
 
+mod openlp;
+
+// Modules shared with RustLP
+#[allow(dead_code)]
+#[path = "../../src/chordpro.rs"]
+mod chordpro;
+#[allow(dead_code)]
+#[path = "../../src/chords.rs"]
+mod chords;
+#[allow(dead_code)]
+#[path = "../../src/library.rs"]
+mod library;
+#[allow(dead_code)]
+#[path = "../../src/openlyrics.rs"]
+mod openlyrics;
+#[allow(dead_code)]
+#[path = "../../src/plaintext.rs"]
+mod plaintext;
+#[allow(dead_code)]
+#[path = "../../src/schema.rs"]
+mod schema;
+#[allow(dead_code)]
+#[path = "../../src/song.rs"]
+mod song;
+
+use library::{Format, Importer, Policy};
+use openlp::TitlePrefix;
+
+const USAGE: &str = "Usage:
+    converter [openlp] <songs.sqlite> [options]  Import an OpenLP database
+    converter import <file>... [options]         Import ChordPro, OpenLyrics or text (SongSelect) files
+    converter export <format> <folder>           Export every song (format: chordpro, openlyrics, text)
+
+Options:
+    --dry-run                Show the report without saving anything
+    --update                 Update the songs already in the library (default: skip)
+    --title-prefix <regex>   Extract the songbook and the number from the OpenLP titles,
+                             for example \"<JEM 123>  Title\" with:
+                             '<(?<book>[A-Z]+) (?<number>[0-9]+)>  (?<title>.+)'
+
+Songs are already in the library if they come from the same file (or OpenLP id),
+or if they have the same title and lyrics.";
+
+struct Options {
+    dry_run: bool,
+    policy: Policy,
+    prefix: Option<TitlePrefix>,
+    // Positional arguments
+    args: Vec<String>,
+}
+
+impl Options {
+    fn parse(args: &[String]) -> Result<Self, ()> {
+        let mut options = Options {
+            dry_run: false,
+            policy: Policy::Skip,
+            prefix: None,
+            args: vec![],
+        };
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--dry-run" => options.dry_run = true,
+                "--update" => options.policy = Policy::Update,
+                "--title-prefix" => {
+                    let regex = args.next().ok_or_else(|| println!("{USAGE}"))?;
+                    options.prefix = Some(TitlePrefix::new(regex).map_err(|e| println!("{e}"))?);
+                }
+                _ if arg.starts_with("--") => {
+                    println!("Unknown option \"{arg}\"\n\n{USAGE}");
+                    return Err(());
+                }
+                _ => options.args.push(arg.clone()),
+            }
+        }
+        Ok(options)
+    }
+}
+
 fn main() -> Result<(), ()> {
-    let args: Vec<String> = std::env::args().collect();
-    if args.len() <= 1 {
-        println!("Please give a file path");
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        None => {
+            println!("{USAGE}");
+            Err(())
+        }
+        Some("import") => import(Options::parse(&args[1..])?),
+        Some("export") => export(&args[1..]),
+        Some("openlp") => openlp(Options::parse(&args[1..])?),
+        Some(_) => openlp(Options::parse(&args)?),
+    }
+}
+
+// Run the import in a transaction, cancelled for a dry run
+fn run(options: &Options, f: impl FnOnce(&mut Importer) -> rusqlite::Result<()>) -> Result<(), ()> {
+    let mut db = schema::open().expect("Failed to open the database");
+    let transaction = db.transaction().expect("Failed to start a transaction");
+    let mut importer = Importer::new(&transaction, options.policy).map_err(|e| println!("{e}"))?;
+    let result = f(&mut importer);
+    let report = importer.report;
+    if let Err(e) = result {
+        println!("Import cancelled: {e}");
         return Err(());
     }
-    let file = &args[1];
+    if options.dry_run {
+        println!("Dry run, nothing was saved");
+    } else {
+        transaction.commit().expect("Failed to save the songs");
+    }
+    println!("{report}");
+    if report.failed == 0 { Ok(()) } else { Err(()) }
+}
+
+fn openlp(options: Options) -> Result<(), ()> {
+    let [file] = options.args.as_slice() else {
+        println!("{USAGE}");
+        return Err(());
+    };
     if !Path::new(file).exists() {
         println!("\"{file}\" is not a valid file");
         return Err(());
     }
-    run(file).unwrap().close().unwrap();
-    Ok(())
+    run(&options, |importer| {
+        openlp::import(importer, file, options.prefix.as_ref())
+    })
 }
-fn run(file: &String) -> rusqlite::Result<Connection> {
-    // Open new database
-    let db = Connection::open(format!(
-        "{}/Documents/songs.sqlite",
-        std::env::var("HOME").expect("The HOME variable is not defined")
-    ))?;
-
-    // Open Open-LP database
-    db.execute("ATTACH DATABASE ? AS old;", &[(file)])?;
-    db.execute_batch(
-        "CREATE TABLE IF NOT EXISTS songs (
-                id     INTEGER PRIMARY KEY AUTOINCREMENT,
-                title  VARCHAR(255),
-                lyrics TEXT NOT NULL,
-                book   INTEGER,
-                number INTEGER
-        );
-        CREATE TABLE IF NOT EXISTS authors (
-                id     INTEGER PRIMARY KEY,
-                name   VARCHAR(255)
-        );
-        CREATE TABLE IF NOT EXISTS authors_songs (
-                author_id INTEGER NOT NULL,
-                song_id   INTEGER NOT NULL
-        );
-        CREATE TABLE IF NOT EXISTS books (
-                id   INTEGER PRIMARY KEY,
-                name VARCHAR(255)
-        );
-        INSERT INTO songs (title,lyrics) SELECT title,lyrics FROM old.songs;
-        INSERT INTO books (name) VALUES ('JEM'), ('JEMK'), ('ATG');
-        INSERT INTO authors (id,name) SELECT id,display_name FROM old.authors;
-
-        INSERT INTO authors_songs (author_id,song_id)
-        SELECT oas.author_id,s.id
-        FROM old.authors_songs oas
-        JOIN old.songs os ON oas.song_id = os.id
-        JOIN songs s ON os.title = s.title;
-        ",
-    )?;
 
-    // Set the songbook id of each song (based on the old song title)
-    let mut number = db.prepare("UPDATE songs SET book = ? WHERE title LIKE ?;")?;
-    number.execute(["1", "<JEM %>  %"])?;
-    number.execute(["2", "<JEMK %>  %"])?;
-    number.execute(["3", "<ATG %>  %"])?;
-    number.finalize()?;
-
-    // Remove songbook and number from the beginning of the title
-    let mut query = db.prepare("SELECT title FROM songs WHERE book IS NOT NULL;")?;
-    let result = query.query_map([], |row| Ok(row.get::<_, String>(0)?))?; // get the titles to change
-    let regex = Regex::new(r"<[A-Z]+ ([0-9]+)>  (.+)").unwrap();
-    let mut update =
-        db.prepare_cached("UPDATE songs SET title = ?, number = ? WHERE title = ?;")?; // request to change title
+fn import(options: Options) -> Result<(), ()> {
+    run(&options, |importer| {
+        for file in &options.args {
+            importer.import_file(Path::new(file));
+        }
+        Ok(())
+    })
+}
 
-    let start = std::time::Instant::now();
-    for i in result {
-        let title = i?;
-        if let Some(split) = regex.captures(&title) {
-            update.execute((
-                split.get(2).unwrap().as_str(),
-                split.get(1).unwrap().as_str(),
-                &title,
-            ))?; // change the title
+fn export(args: &[String]) -> Result<(), ()> {
+    let [format, folder] = args else {
+        println!("{USAGE}");
+        return Err(());
+    };
+    let Some(format) = Format::ALL
+        .into_iter()
+        .find(|f| f.to_string().eq_ignore_ascii_case(format))
+    else {
+        println!("Unknown format \"{format}\"");
+        return Err(());
+    };
+    let folder = Path::new(folder);
+    std::fs::create_dir_all(folder).map_err(|e| println!("{e}"))?;
+    let db = schema::open().expect("Failed to open the database");
+    let mut query = db.prepare("SELECT id FROM songs;").unwrap();
+    let ids: Vec<u16> = query
+        .query_map([], |row| row.get(0))
+        .unwrap()
+        .collect::<rusqlite::Result<_>>()
+        .unwrap();
+    for id in ids {
+        if let Err(e) = library::export_song(&db, id, format, folder) {
+            println!("Failed to export song {id}: {e}");
         }
     }
-    let stop = start.elapsed();
-    println!("Changing songbook number takes {} ms.", stop.as_millis());
-    query.finalize()?;
-    update.discard();
-
-    db.flush_prepared_statement_cache();
-    Ok(db)
+    Ok(())
 }
@@ -0,0 +1,174 @@
+// Import an OpenLP database, every link (books, authors, topics) is followed by id
+use regex::Regex;
+use rusqlite::{Connection, OpenFlags, OptionalExtension, Result};
+use std::collections::HashMap;
+
+use crate::{
+    library::{Importer, Record},
+    song::Song,
+};
+
+// Extract the songbook and the number from titles like "<JEM 123>  Title", the
+// regex must have the named groups "book", "number" and "title":
+// <(?<book>[A-Z]+) (?<number>[0-9]+)>  (?<title>.+)
+pub struct TitlePrefix(Regex);
+
+impl TitlePrefix {
+    pub fn new(regex: &str) -> std::result::Result<Self, String> {
+        let regex = Regex::new(regex).map_err(|e| e.to_string())?;
+        for group in ["book", "number", "title"] {
+            if !regex.capture_names().flatten().any(|name| name == group) {
+                return Err(format!("The title prefix has no \"{group}\" group"));
+            }
+        }
+        Ok(Self(regex))
+    }
+
+    fn split<'a>(&self, title: &'a str) -> Option<(&'a str, u16, &'a str)> {
+        let captures = self.0.captures(title)?;
+        Some((
+            captures.name("book")?.as_str(),
+            captures.name("number")?.as_str().parse().ok()?,
+            captures.name("title")?.as_str(),
+        ))
+    }
+}
+
+pub fn import(importer: &mut Importer, file: &str, prefix: Option<&TitlePrefix>) -> Result<()> {
+    let old = Connection::open_with_flags(file, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let source = std::fs::canonicalize(file)
+        .map(|path| path.to_string_lossy().to_string())
+        .unwrap_or(file.to_string());
+
+    // Songbooks, authors and topics: old id -> name
+    let books = names(&old, "SELECT id, name FROM song_books;")?;
+    let authors = names(&old, "SELECT id, display_name FROM authors;")?;
+    let topics = if has_table(&old, "topics")? {
+        names(&old, "SELECT id, name FROM topics;")?
+    } else {
+        HashMap::new()
+    };
+
+    // Song id -> (book id, entry), OpenLP 2.0 stored them in the songs table
+    let entries: HashMap<u32, (u32, String)> = if has_table(&old, "songs_songbooks")? {
+        let mut query = old.prepare("SELECT song_id, songbook_id, entry FROM songs_songbooks;")?;
+        query
+            .query_map([], |row| Ok((row.get(0)?, (row.get(1)?, row.get(2)?))))?
+            .collect::<Result<_>>()?
+    } else {
+        let mut query = old.prepare(
+            "SELECT id, song_book_id, song_number FROM songs WHERE song_book_id IS NOT NULL;",
+        )?;
+        query
+            .query_map([], |row| {
+                Ok((
+                    row.get(0)?,
+                    (
+                        row.get(1)?,
+                        row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+                    ),
+                ))
+            })?
+            .collect::<Result<_>>()?
+    };
+    let song_authors = links(&old, "SELECT song_id, author_id FROM authors_songs;")?;
+    let song_topics = if has_table(&old, "songs_topics")? {
+        links(&old, "SELECT song_id, topic_id FROM songs_topics;")?
+    } else {
+        HashMap::new()
+    };
+
+    let mut query = old.prepare(
+        "SELECT id, title, alternate_title, lyrics, verse_order, copyright, comments, ccli_number
+            FROM songs;",
+    )?;
+    let mut rows = query.query([])?;
+    while let Some(row) = rows.next()? {
+        let id: u32 = row.get(0)?;
+        let mut book = None;
+        let mut song = Song {
+            title: row.get(1)?,
+            alternate_title: row.get::<_, Option<String>>(2)?.filter(|t| !t.is_empty()),
+            lyrics: Song::parse_lyrics(row.get(3)?).unwrap_or_default(),
+            verse_order: row.get::<_, Option<String>>(4)?.filter(|o| !o.is_empty()),
+            copyright: row.get::<_, Option<String>>(5)?.filter(|c| !c.is_empty()),
+            comments: row.get::<_, Option<String>>(6)?.filter(|c| !c.is_empty()),
+            ccli_number: row.get::<_, Option<String>>(7)?.filter(|c| !c.is_empty()),
+            ..Default::default()
+        };
+        if let Some((book_id, entry)) = entries.get(&id)
+            && let Some(name) = books.get(book_id)
+        {
+            book = Some(name.clone());
+            song.number = number(entry);
+        }
+        if let Some(prefix) = prefix
+            && let Some((name, number, title)) = prefix.split(&song.title)
+        {
+            if book.is_none() {
+                book = Some(name.to_string());
+                song.number = Some(number);
+            }
+            song.title = title.to_string();
+        }
+        let names = |links: &HashMap<u32, Vec<u32>>, names: &HashMap<u32, String>| {
+            links
+                .get(&id)
+                .into_iter()
+                .flatten()
+                .filter_map(|id| names.get(id).cloned())
+                .collect::<Vec<String>>()
+        };
+        let record = Record {
+            authors: names(&song_authors, &authors),
+            topics: names(&song_topics, &topics),
+            book,
+            song,
+        };
+        importer.import(&record, Some(&format!("{}#{}", source, id)));
+    }
+    Ok(())
+}
+
+fn has_table(db: &Connection, name: &str) -> Result<bool> {
+    Ok(db
+        .query_row(
+            "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?;",
+            [name],
+            |_| Ok(()),
+        )
+        .optional()?
+        .is_some())
+}
+
+fn names(db: &Connection, sql: &str) -> Result<HashMap<u32, String>> {
+    let mut query = db.prepare(sql)?;
+    query
+        .query_map([], |row| {
+            Ok((
+                row.get(0)?,
+                row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+            ))
+        })?
+        .collect()
+}
+
+fn links(db: &Connection, sql: &str) -> Result<HashMap<u32, Vec<u32>>> {
+    let mut links: HashMap<u32, Vec<u32>> = HashMap::new();
+    let mut query = db.prepare(sql)?;
+    let mut rows = query.query([])?;
+    while let Some(row) = rows.next()? {
+        links.entry(row.get(0)?).or_default().push(row.get(1)?);
+    }
+    Ok(links)
+}
+
+// Songbook entries are free text in OpenLP ("123", "123a")
+fn number(entry: &str) -> Option<u16> {
+    let digits: String = entry
+        .trim()
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+    digits.parse().ok()
+}
@@ -0,0 +1,193 @@
+// Backgrounds of the songs and the service items, the images are kept in a folder
+use iced::{
+    Background, Color, Element, Length,
+    widget::{Column, button, column, container, horizontal_space, image, row},
+};
+use std::path::{Path, PathBuf};
+
+use crate::{App, Message, schema, widget::ttext};
+
+pub const IMAGE_EXTENSIONS: [&str; 6] = ["png", "jpg", "jpeg", "bmp", "gif", "webp"];
+const PALETTE: [u32; 6] = [0x000000, 0xffffff, 0x1a237e, 0x1b5e20, 0x4a148c, 0x424242];
+const THUMBNAIL_HEIGHT: f32 = 54.0;
+const COLUMNS: usize = 6;
+
+// Saved as None for the default, "#rrggbb" for a color, or the name of an image
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Backdrop {
+    Default,
+    Color(u32),
+    Image(String),
+}
+
+impl Backdrop {
+    pub fn parse(spec: Option<&str>) -> Self {
+        match spec.map(str::trim) {
+            None | Some("") => Backdrop::Default,
+            Some(spec) => match spec
+                .strip_prefix('#')
+                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+            {
+                Some(rgb) => Backdrop::Color(rgb),
+                None => Backdrop::Image(spec.to_string()),
+            },
+        }
+    }
+
+    pub fn spec(&self) -> Option<String> {
+        match self {
+            Backdrop::Default => None,
+            Backdrop::Color(rgb) => Some(format!("#{:06x}", rgb)),
+            Backdrop::Image(name) => Some(name.clone()),
+        }
+    }
+}
+
+pub fn color(rgb: u32) -> Color {
+    Color::from_rgb8((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8)
+}
+
+// Next to the database
+pub fn folder() -> PathBuf {
+    Path::new(&schema::path()).with_file_name("backgrounds")
+}
+
+pub fn path(name: &str) -> PathBuf {
+    folder().join(name)
+}
+
+// Names of the images of the folder
+pub fn load_library() -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(folder()) else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = entries
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            let extension = path.extension()?.to_str()?.to_lowercase();
+            if !IMAGE_EXTENSIONS.contains(&extension.as_str()) {
+                return None;
+            }
+            path.file_name()?.to_str().map(str::to_string)
+        })
+        .collect();
+    names.sort();
+    names
+}
+
+// Copy the images in the folder, an image with the same name is replaced
+pub fn import(files: &[PathBuf]) {
+    if let Err(e) = std::fs::create_dir_all(folder()) {
+        println!("ERROR: Failed to create {}: {}", folder().display(), e);
+        return;
+    }
+    for file in files {
+        let Some(name) = file.file_name() else {
+            continue;
+        };
+        if let Err(e) = std::fs::copy(file, folder().join(name)) {
+            println!("ERROR: Failed to import {}: {}", file.display(), e);
+        }
+    }
+}
+
+impl App {
+    pub fn pick_backdrops(&self) -> iced::Task<Message> {
+        iced::Task::perform(
+            rfd::AsyncFileDialog::new()
+                .add_filter("Images", &IMAGE_EXTENSIONS)
+                .pick_files(),
+            |files| {
+                files.map(|files| {
+                    Message::ImportBackdrops(
+                        files.iter().map(|file| file.path().to_path_buf()).collect(),
+                    )
+                })
+            },
+        )
+        .and_then(iced::Task::done)
+    }
+
+    // Default, colors and images of the library as thumbnails, the selected one is highlighted
+    pub fn view_backdrops<'a>(
+        &'a self,
+        selected: Option<&str>,
+        action: impl Fn(Option<String>) -> Message + 'a,
+    ) -> Column<'a, Message> {
+        let selected = Backdrop::parse(selected);
+        let choices = [Backdrop::Default]
+            .into_iter()
+            .chain(PALETTE.into_iter().map(Backdrop::Color))
+            .chain(self.backdrops.iter().cloned().map(Backdrop::Image));
+        let thumbnails: Vec<Element<'a, Message>> = choices
+            .map(|backdrop| {
+                let content: Element<'a, Message> = match &backdrop {
+                    Backdrop::Default => ttext("Default", self).center().into(),
+                    Backdrop::Color(rgb) => {
+                        let rgb = *rgb;
+                        container(horizontal_space())
+                            .style(move |_| container::Style {
+                                background: Some(Background::Color(color(rgb))),
+                                ..Default::default()
+                            })
+                            .into()
+                    }
+                    Backdrop::Image(name) => image(path(name)).into(),
+                };
+                button(container(content).center(Length::Fill))
+                    .width(Length::FillPortion(1))
+                    .height(THUMBNAIL_HEIGHT)
+                    .padding(3)
+                    .style(if backdrop == selected {
+                        button::primary
+                    } else {
+                        button::text
+                    })
+                    .on_press(action(backdrop.spec()))
+                    .into()
+            })
+            .collect();
+        let mut picker = Column::new().spacing(self.set.spacing);
+        let mut thumbnails = thumbnails.into_iter().peekable();
+        while thumbnails.peek().is_some() {
+            let mut line = row![].spacing(self.set.spacing);
+            for index in 0..COLUMNS {
+                line = match thumbnails.next() {
+                    Some(thumbnail) => line.push(thumbnail),
+                    // The last row keeps the width of the others
+                    None if index > 0 => {
+                        line.push(horizontal_space().width(Length::FillPortion(1)))
+                    }
+                    None => line,
+                };
+            }
+            picker = picker.push(line);
+        }
+        column![
+            picker,
+            row![
+                horizontal_space(),
+                button(ttext("Import images", self))
+                    .style(button::secondary)
+                    .on_press(Message::PickBackdrops),
+            ],
+        ]
+        .spacing(self.set.spacing)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn backdrop_spec() {
+        assert_eq!(Backdrop::parse(None), Backdrop::Default);
+        assert_eq!(Backdrop::parse(Some("#1a237e")), Backdrop::Color(0x1a237e));
+        assert_eq!(Backdrop::Color(0xff).spec().as_deref(), Some("#0000ff"));
+        assert_eq!(
+            Backdrop::parse(Some("sunrise.jpg")),
+            Backdrop::Image(String::from("sunrise.jpg"))
+        );
+    }
+}
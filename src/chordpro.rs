@@ -0,0 +1,143 @@
+// ChordPro files (.cho, .chopro): https://www.chordpro.org/chordpro/chordpro-directives/
+use crate::song::{Song, Verse, VerseType};
+
+// Parse a ChordPro file, return the song and its authors
+pub fn parse(input: &str) -> (Song, Vec<String>) {
+    let mut song = Song::default();
+    let mut authors = vec![];
+    let mut comments: Vec<String> = vec![];
+    let mut section: Option<Verse> = None;
+    let mut text = String::new();
+    for line in input.lines() {
+        let line = line.trim_end();
+        if line.starts_with('#') {
+            continue;
+        }
+        let Some(directive) = line
+            .trim_start()
+            .strip_prefix('{')
+            .and_then(|line| line.strip_suffix('}'))
+        else {
+            if line.trim().is_empty() && section.is_none() {
+                song.push_verse(None, &mut text);
+            } else {
+                text += line;
+                text += "\n";
+            }
+            continue;
+        };
+        let (name, value) = match directive.split_once(':') {
+            Some((name, value)) => (name.trim().to_lowercase(), value.trim().to_string()),
+            None => (directive.trim().to_lowercase(), String::new()),
+        };
+        let start_of = match name.as_str() {
+            "start_of_verse" | "sov" => Some(VerseType::Verse),
+            "start_of_chorus" | "soc" => Some(VerseType::Chorus),
+            "start_of_bridge" | "sob" => Some(VerseType::Bridge),
+            _ => None,
+        };
+        if let Some(versetype) = start_of {
+            song.push_verse(section.take(), &mut text);
+            section = Some(start(versetype, &value));
+            continue;
+        }
+        match name.as_str() {
+            "title" | "t" => song.title = value,
+            "subtitle" | "st" => song.alternate_title = Some(value),
+            "copyright" => song.copyright = Some(value),
+            "ccli" => song.ccli_number = Some(value),
+            "artist" | "composer" | "lyricist" if !authors.contains(&value) => authors.push(value),
+            "comment" | "c" | "comment_italic" | "ci" | "comment_box" | "cb" => {
+                comments.push(value)
+            }
+            "end_of_verse" | "eov" | "end_of_chorus" | "eoc" | "end_of_bridge" | "eob" => {
+                song.push_verse(section.take(), &mut text);
+            }
+            _ => (),
+        }
+    }
+    song.push_verse(section, &mut text);
+    if !comments.is_empty() {
+        song.comments = Some(comments.join("\n"));
+    }
+    (song, authors)
+}
+
+// The label of a section may give its real type: {start_of_verse: Pre-Chorus}
+fn start(versetype: VerseType, label: &str) -> Verse {
+    Verse::from_label(label).unwrap_or(Verse::new(versetype, 0))
+}
+
+pub fn export(song: &Song, authors: &[String]) -> String {
+    let mut file = format!("{{title: {}}}\n", song.title);
+    if let Some(subtitle) = &song.alternate_title {
+        file += &format!("{{subtitle: {}}}\n", subtitle);
+    }
+    for author in authors {
+        file += &format!("{{artist: {}}}\n", author);
+    }
+    if let Some(copyright) = &song.copyright {
+        file += &format!("{{copyright: {}}}\n", copyright);
+    }
+    if let Some(ccli) = &song.ccli_number {
+        file += &format!("{{ccli: {}}}\n", ccli);
+    }
+    for comment in song.comments.iter().flat_map(|comments| comments.lines()) {
+        file += &format!("{{comment: {}}}\n", comment);
+    }
+    for (verse, text) in &song.lyrics {
+        let section = match verse.kind() {
+            VerseType::Chorus => "chorus",
+            VerseType::Bridge => "bridge",
+            _ => "verse",
+        };
+        file += &format!(
+            "\n{{start_of_{}: {} {}}}\n{}\n{{end_of_{}}}\n",
+            section,
+            verse.kind().name(),
+            verse.number(),
+            text,
+            section
+        );
+    }
+    file
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn chordpro() {
+        let input = "{title: Amazing Grace}
+{st: New Britain}
+{artist: John Newton}
+{c: Capo 2}
+
+[G]Amazing [G7]grace how [C]sweet the [G]sound
+That saved a wretch like me
+
+{soc}
+[G]My chains are gone
+{eoc}
+
+{start_of_verse: Pre-Chorus}
+And like a flood
+{end_of_verse}
+
+I once was lost
+";
+        let (song, authors) = parse(input);
+        assert_eq!(song.title, "Amazing Grace");
+        assert_eq!(song.alternate_title.as_deref(), Some("New Britain"));
+        assert_eq!(song.comments.as_deref(), Some("Capo 2"));
+        assert_eq!(authors, ["John Newton"]);
+        let verses: Vec<String> = song.lyrics.iter().map(|l| l.0.to_string()).collect();
+        assert_eq!(verses, ["V1", "C1", "P1", "V2"]);
+        assert_eq!(song.lyrics[1].1, "[G]My chains are gone");
+        // Export then import again
+        let (again, authors) = parse(&export(&song, &authors));
+        assert_eq!(again, song);
+        assert_eq!(authors, ["John Newton"]);
+    }
+}
@@ -0,0 +1,154 @@
+// Chords are stored inline in the lyrics with the ChordPro syntax: "[G]Amazing [D]grace"
+
+const SHARPS: [&str; 12] = [
+    "C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B",
+];
+const FLATS: [&str; 12] = [
+    "C", "Db", "D", "Eb", "E", "F", "Gb", "G", "Ab", "A", "Bb", "B",
+];
+
+// Convert OpenLyrics chords (<chord name="G"/>) to the inline syntax
+pub fn normalize(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("<chord") {
+        result.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('>') else {
+            rest = &rest[start..];
+            break;
+        };
+        let tag = &rest[start..start + end + 1];
+        if let Some(name) = tag
+            .split("name=\"")
+            .nth(1)
+            .and_then(|s| s.split('"').next())
+        {
+            result.push_str(&format!("[{}]", name));
+        }
+        rest = &rest[start + end + 1..];
+    }
+    result.push_str(rest);
+    result.replace("</chord>", "")
+}
+
+// Remove every chord of the text (for the congregation display)
+pub fn strip(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut in_chord = false;
+    for c in text.chars() {
+        match c {
+            '[' => in_chord = true,
+            ']' if in_chord => in_chord = false,
+            _ if !in_chord => result.push(c),
+            _ => (),
+        }
+    }
+    result
+}
+
+// Split a line in a chord line and a lyrics line, each chord being above its syllable
+pub fn split(line: &str) -> (String, String) {
+    let (mut chords, mut lyrics) = (String::new(), String::new());
+    let (mut chords_len, mut lyrics_len) = (0, 0);
+    let mut chord = None::<String>;
+    for c in line.chars() {
+        match (&mut chord, c) {
+            (None, '[') => chord = Some(String::new()),
+            (Some(name), ']') => {
+                // Keep a space between two chords
+                if chords_len > 0 && chords_len >= lyrics_len {
+                    let padding = chords_len + 1 - lyrics_len;
+                    lyrics.extend(std::iter::repeat_n(' ', padding));
+                    lyrics_len += padding;
+                }
+                chords.extend(std::iter::repeat_n(' ', lyrics_len - chords_len));
+                chords.push_str(name);
+                chords_len = lyrics_len + name.chars().count();
+                chord = None;
+            }
+            (Some(name), c) => name.push(c),
+            (None, c) => {
+                lyrics.push(c);
+                lyrics_len += 1;
+            }
+        }
+    }
+    (chords, lyrics)
+}
+
+// Transpose every chord of the text
+pub fn transpose_text(text: &str, semitones: i8) -> String {
+    if semitones == 0 {
+        return text.to_string();
+    }
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('[') {
+        let Some(end) = rest[start..].find(']') else {
+            break;
+        };
+        result.push_str(&rest[..=start]);
+        result.push_str(&transpose(&rest[start + 1..start + end], semitones));
+        result.push(']');
+        rest = &rest[start + end + 1..];
+    }
+    result.push_str(rest);
+    result
+}
+
+// Transpose a chord like "F#m7/C#", unknown chords are returned unchanged
+pub fn transpose(chord: &str, semitones: i8) -> String {
+    match chord.split_once('/') {
+        Some((chord, bass)) => format!(
+            "{}/{}",
+            transpose_note(chord, semitones),
+            transpose_note(bass, semitones)
+        ),
+        None => transpose_note(chord, semitones),
+    }
+}
+
+fn transpose_note(chord: &str, semitones: i8) -> String {
+    let root_len = match chord.chars().nth(1) {
+        Some('#') | Some('b') => 2,
+        _ => 1,
+    };
+    let Some(root) = chord.get(..root_len) else {
+        return chord.to_string();
+    };
+    let Some(index) = SHARPS
+        .iter()
+        .position(|note| *note == root)
+        .or_else(|| FLATS.iter().position(|note| *note == root))
+    else {
+        return chord.to_string();
+    };
+    let index = (index as i8 + semitones).rem_euclid(12) as usize;
+    let notes = if root.ends_with('b') { FLATS } else { SHARPS };
+    format!("{}{}", notes[index], &chord[root_len..])
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn chords() {
+        let line = "[G]Amazing [D/F#]grace how [Em]sweet";
+        assert_eq!(strip(line), "Amazing grace how sweet");
+        assert_eq!(
+            split(line),
+            (
+                String::from("G       D/F#      Em"),
+                String::from("Amazing grace how sweet")
+            )
+        );
+        assert_eq!(
+            transpose_text(line, 2),
+            "[A]Amazing [E/G#]grace how [F#m]sweet"
+        );
+        assert_eq!(transpose("Bb7", -3), "G7");
+        assert_eq!(transpose("N.C.", 1), "N.C.");
+        assert_eq!(normalize("<chord name=\"G\"/>Amazing"), "[G]Amazing");
+    }
+}
@@ -1,39 +1,20 @@
 use iced::Task;
-use rusqlite::{Connection, Result};
-use std::cmp::Ordering;
+use rusqlite::{Connection, Result, params};
+use serde::{Deserialize, Serialize};
+use std::{cmp::Ordering, path::Path};
 
 use crate::{
     Message,
     control::Content,
+    library::{Format, load_song},
+    media::Media,
+    schema,
     song::{Book, Song},
+    timer::Timer,
 };
 
 pub fn connect_db() -> Result<Connection> {
-    let db = Connection::open(format!(
-        "{}/Documents/songs.sqlite",
-        std::env::var("HOME").unwrap_or(String::new())
-    ))?;
-    db.execute_batch(
-        "CREATE TABLE IF NOT EXISTS songs (
-                id     INTEGER PRIMARY KEY AUTOINCREMENT,
-                title  VARCHAR(255),
-                lyrics TEXT NOT NULL,
-                book   INTEGER,
-                number INTEGER
-        );
-        CREATE TABLE IF NOT EXISTS authors (
-                id     INTEGER PRIMARY KEY,
-                name   VARCHAR(255)
-        );
-        CREATE TABLE IF NOT EXISTS authors_songs (
-                author_id INTEGER NOT NULL,
-                song_id   INTEGER NOT NULL
-        );
-        CREATE TABLE IF NOT EXISTS books (
-                id   INTEGER PRIMARY KEY,
-                name VARCHAR(255)
-        );",
-    )?;
+    let db = schema::open()?;
     db.create_collation("NOACCENTS", noaccents)?;
     Ok(db)
 }
@@ -50,7 +31,7 @@ fn noaccents(title: &str, query: &str) -> Ordering {
 }
 
 pub fn load_songbooks(db: &Connection) -> Result<Vec<Book>> {
-    let mut query = db.prepare("SELECT * FROM books;")?;
+    let mut query = db.prepare("SELECT id, name, publisher, abbreviation FROM books;")?;
     let mut iterator = query.query([])?;
     let mut books: Vec<Book> = Vec::new();
     while let Ok(Some(book)) = iterator.next() {
@@ -59,11 +40,232 @@ pub fn load_songbooks(db: &Connection) -> Result<Vec<Book>> {
     Ok(books)
 }
 
-pub fn load_index(db: &Connection, sort: Sort, search: &String) -> Result<Vec<(u16, String)>> {
+// Authors and songbooks, edited in the management window
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    #[default]
+    Authors,
+    Books,
+    Topics,
+}
+
+impl Kind {
+    pub const ALL: [Kind; 3] = [Kind::Authors, Kind::Books, Kind::Topics];
+
+    fn table(&self) -> &'static str {
+        match self {
+            Kind::Authors => "authors",
+            Kind::Books => "books",
+            Kind::Topics => "topics",
+        }
+    }
+}
+
+impl std::fmt::Display for Kind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+// An author or a songbook with its number of songs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub id: u16,
+    pub name: String,
+    pub songs: usize,
+}
+
+impl std::fmt::Display for Entry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({})", self.name, self.songs)
+    }
+}
+
+pub fn load_entries(db: &Connection, kind: Kind) -> Result<Vec<Entry>> {
+    let mut query = db.prepare(match kind {
+        Kind::Authors => {
+            "SELECT a.id, a.name, COUNT(asng.song_id)
+                FROM authors a
+                LEFT JOIN authors_songs asng ON a.id = asng.author_id
+                GROUP BY a.id
+                ORDER BY a.name;"
+        }
+        Kind::Books => {
+            "SELECT b.id, b.name, COUNT(s.id)
+                FROM books b
+                LEFT JOIN songs s ON s.book = b.id
+                GROUP BY b.id
+                ORDER BY b.name;"
+        }
+        Kind::Topics => {
+            "SELECT t.id, t.name, COUNT(st.song_id)
+                FROM topics t
+                LEFT JOIN songs_topics st ON t.id = st.topic_id
+                GROUP BY t.id
+                ORDER BY t.name;"
+        }
+    })?;
+    query
+        .query_map([], |row| {
+            Ok(Entry {
+                id: row.get(0)?,
+                name: row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+                songs: row.get(2)?,
+            })
+        })?
+        .collect()
+}
+
+pub fn rename_entry(db: &Connection, kind: Kind, id: u16, name: &str) -> Result<()> {
+    db.execute(
+        &format!("UPDATE {} SET name = ? WHERE id = ?;", kind.table()),
+        params![name, id],
+    )?;
+    Ok(())
+}
+
+// Move the songs of an author or a songbook to another one, then delete it
+pub fn merge_entry(db: &Connection, kind: Kind, from: u16, into: u16) -> Result<()> {
+    if from == into {
+        return Ok(());
+    }
+    let transaction = db.unchecked_transaction()?;
+    match kind {
+        Kind::Authors => {
+            // A song may already have both authors
+            db.execute(
+                "DELETE FROM authors_songs WHERE author_id = ?1 AND song_id IN
+                    (SELECT song_id FROM authors_songs WHERE author_id = ?2);",
+                [from, into],
+            )?;
+            db.execute(
+                "UPDATE authors_songs SET author_id = ?2 WHERE author_id = ?1;",
+                [from, into],
+            )?;
+        }
+        Kind::Books => {
+            db.execute("UPDATE songs SET book = ?2 WHERE book = ?1;", [from, into])?;
+        }
+        Kind::Topics => {
+            db.execute(
+                "DELETE FROM songs_topics WHERE topic_id = ?1 AND song_id IN
+                    (SELECT song_id FROM songs_topics WHERE topic_id = ?2);",
+                [from, into],
+            )?;
+            db.execute(
+                "UPDATE songs_topics SET topic_id = ?2 WHERE topic_id = ?1;",
+                [from, into],
+            )?;
+        }
+    }
+    delete_entry(db, kind, from)?;
+    transaction.commit()
+}
+
+// The songs are kept, without this author or songbook
+pub fn delete_entry(db: &Connection, kind: Kind, id: u16) -> Result<()> {
+    match kind {
+        Kind::Authors => db.execute("DELETE FROM authors_songs WHERE author_id = ?;", [id])?,
+        Kind::Books => db.execute(
+            "UPDATE songs SET book = NULL, number = NULL WHERE book = ?;",
+            [id],
+        )?,
+        Kind::Topics => db.execute("DELETE FROM songs_topics WHERE topic_id = ?;", [id])?,
+    };
+    db.execute(&format!("DELETE FROM {} WHERE id = ?;", kind.table()), [id])?;
+    Ok(())
+}
+
+pub fn update_book(db: &Connection, book: &Book) -> Result<()> {
+    db.execute(
+        "UPDATE books SET publisher = ?, abbreviation = ? WHERE id = ?;",
+        params![book.publisher, book.abbreviation, book.id],
+    )?;
+    Ok(())
+}
+
+// Library filters, combined with the search
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Filter {
+    pub topic: Option<Entry>,
+    pub usage: Usage,
+}
+
+impl Filter {
+    // ?2: topic, ?3: sung since, ?4: not sung since
+    const SQL: &str = "(?2 IS NULL OR s.id IN (SELECT song_id FROM songs_topics WHERE topic_id = ?2))
+                AND (?3 IS NULL OR s.id IN
+                    (SELECT song_id FROM song_usage WHERE used_at >= datetime('now', 'localtime', ?3)))
+                AND (?4 IS NULL OR s.id NOT IN
+                    (SELECT song_id FROM song_usage WHERE used_at >= datetime('now', 'localtime', ?4)))";
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Usage {
+    #[default]
+    Any,
+    SungIn(u8),
+    NotSungIn(u8),
+}
+
+impl Usage {
+    pub const ALL: [Usage; 6] = [
+        Usage::Any,
+        Usage::SungIn(4),
+        Usage::NotSungIn(4),
+        Usage::NotSungIn(8),
+        Usage::NotSungIn(12),
+        Usage::NotSungIn(26),
+    ];
+
+    // SQLite date modifiers of the filter
+    fn modifiers(&self) -> (Option<String>, Option<String>) {
+        let days = |weeks: &u8| format!("-{} days", *weeks as u16 * 7);
+        match self {
+            Usage::Any => (None, None),
+            Usage::SungIn(weeks) => (Some(days(weeks)), None),
+            Usage::NotSungIn(weeks) => (None, Some(days(weeks))),
+        }
+    }
+}
+
+impl std::fmt::Display for Usage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Usage::Any => write!(f, "Any usage"),
+            Usage::SungIn(weeks) => write!(f, "Sung in the last {} weeks", weeks),
+            Usage::NotSungIn(weeks) => write!(f, "Not sung in {} weeks", weeks),
+        }
+    }
+}
+
+// Record a song shown on the display, once a day for each service
+pub fn record_usage(db: &Connection, song: u16, service: &str) -> Result<()> {
+    let service = (!service.is_empty()).then_some(service);
+    db.execute(
+        "INSERT INTO song_usage (song_id, used_at, service)
+            SELECT ?1, datetime('now', 'localtime'), ?2
+            WHERE NOT EXISTS (SELECT 1 FROM song_usage
+                WHERE song_id = ?1 AND service IS ?2
+                    AND date(used_at) = date('now', 'localtime'));",
+        params![song, service],
+    )?;
+    Ok(())
+}
+
+pub fn load_index(
+    db: &Connection,
+    sort: Sort,
+    search: &String,
+    filter: &Filter,
+) -> Result<Vec<(u16, String)>> {
     // Query database
     let mut index = vec![];
-    let mut query = db.prepare(Sort::QUERYS[sort as usize])?;
-    let mut iterator = query.query([search])?;
+    let mut query =
+        db.prepare_cached(&Sort::QUERYS[sort as usize].replace("{filter}", Filter::SQL))?;
+    let topic = filter.topic.as_ref().map(|topic| topic.id);
+    let (sung, not_sung) = filter.usage.modifiers();
+    let mut iterator = query.query(params![search, topic, sung, not_sung])?;
     //  Create widgets
     while let Ok(Some(i)) = iterator.next() {
         index.push((
@@ -102,12 +304,6 @@ pub fn load_index(db: &Connection, sort: Sort, search: &String) -> Result<Vec<(u
     Ok(index)
 }
 
-pub fn load_song(db: &Connection, id: u16) -> Result<Song> {
-    let mut query =
-        db.prepare("SELECT id, title, lyrics, book, number FROM songs WHERE id = ?;")?;
-    query.query_one([id], |row| row.try_into())
-}
-
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 pub enum Sort {
     #[default]
@@ -124,7 +320,8 @@ impl Sort {
             FROM songs s
             LEFT JOIN books b
             ON s.book = b.id
-            WHERE s.title = ?1 COLLATE NOACCENTS OR s.number = ?1
+            WHERE (s.title = ?1 COLLATE NOACCENTS OR s.number = ?1)
+                AND {filter}
             GROUP BY s.id
             ORDER BY CASE WHEN b.name IS NULL THEN s.title ELSE b.name END,
                      CASE WHEN b.name IS NULL THEN '' ELSE s.number END;",
@@ -133,6 +330,7 @@ impl Sort {
             JOIN authors_songs asng ON s.id = asng.song_id
             JOIN authors a ON asng.author_id = a.id
             WHERE s.title = ?1 COLLATE NOACCENTS
+                AND {filter}
             GROUP BY s.id
             ORDER BY s.title;",
         "SELECT s.id, b.name, s.number, s.title
@@ -140,12 +338,14 @@ impl Sort {
             JOIN books b
             ON s.book = b.id
             WHERE s.number = ?1
+                AND {filter}
             ORDER BY CASE WHEN b.name IS NULL THEN 1 ELSE 0 END, b.name;",
         "SELECT s.id, a.name, s.title
             FROM authors_songs asng
             JOIN authors a ON a.id = asng.author_id
             JOIN songs s ON s.id = asng.song_id
             WHERE a.name = ?1 COLLATE NOACCENTS
+                AND {filter}
             ORDER BY a.name,s.title;",
     ];
 }
@@ -156,48 +356,283 @@ impl std::fmt::Display for Sort {
     }
 }
 
-#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
 pub enum Status {
     #[default]
     DarkScreen,
     WhiteScreen,
     Song,
+    // Logo or announcement image of the settings
+    Logo,
+    // Slide kept on the display while the live pane moves
+    Freeze,
+    // Background of the theme without the text
+    Background,
+}
+
+// Entry of the service, a placeholder holds the place of a song still to choose
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Item {
+    // Name of the slot of the template
+    pub slot: Option<String>,
+    pub kind: ItemKind,
+    // Seconds between the slides of a loop, 0 for no loop
+    pub interval: u32,
+    // Replaces the background of the song
+    pub background: Option<String>,
+    // Cues for the leaders, like "repeat the last chorus"
+    pub notes: String,
+    // Planned seconds, 0 for no plan
+    pub duration: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ItemKind {
+    Song(Song),
+    Placeholder,
+    Timer(Timer),
+    Media(Media),
+}
+
+impl Item {
+    pub fn new(slot: Option<String>, kind: ItemKind) -> Self {
+        Item {
+            slot,
+            kind,
+            interval: 0,
+            background: None,
+            notes: String::new(),
+            duration: 0,
+        }
+    }
+
+    pub fn song(song: Song) -> Self {
+        Item::new(None, ItemKind::Song(song))
+    }
+
+    pub fn placeholder(slot: String) -> Self {
+        Item::new(Some(slot), ItemKind::Placeholder)
+    }
+
+    pub fn timer(timer: Timer) -> Self {
+        Item::new(None, ItemKind::Timer(timer))
+    }
+
+    pub fn media(media: Media) -> Self {
+        Item::new(None, ItemKind::Media(media))
+    }
+
+    pub fn as_song(&self) -> Option<&Song> {
+        match &self.kind {
+            ItemKind::Song(song) => Some(song),
+            _ => None,
+        }
+    }
+
+    pub fn as_timer(&self) -> Option<&Timer> {
+        match &self.kind {
+            ItemKind::Timer(timer) => Some(timer),
+            _ => None,
+        }
+    }
+
+    pub fn as_media(&self) -> Option<&Media> {
+        match &self.kind {
+            ItemKind::Media(media) => Some(media),
+            _ => None,
+        }
+    }
+
+    pub fn title(&self, books: &[Book]) -> String {
+        let title = match &self.kind {
+            ItemKind::Song(song) => song.title(books),
+            ItemKind::Timer(timer) => timer.title(),
+            ItemKind::Media(media) => media.title(),
+            ItemKind::Placeholder => {
+                return format!("[{}]", self.slot.as_deref().unwrap_or_default());
+            }
+        };
+        match &self.slot {
+            Some(slot) => format!("{}: {}", slot, title),
+            None => title,
+        }
+    }
 }
 
 #[derive(Debug, Default, Clone)]
 pub struct Service {
-    list: Vec<Song>,
+    pub name: String,
+    list: Vec<Item>,
     current: usize,
     preview: Option<Song>,
+    // Copy of the song of each content when it was frozen
+    pub frozen: [Option<Song>; 2],
     pub status: [Status; 2],
+    // Lines per slide at most, 0 for no limit
+    pub max_lines: usize,
+    // The loops play, or are paused
+    pub looping: bool,
 }
 
 impl Service {
     pub fn new() -> Self {
         Service {
+            name: String::new(),
             list: Vec::with_capacity(10),
             current: 0,
             preview: None,
+            frozen: [None, None],
             status: [Status::default(), Status::default()],
+            max_lines: 0,
+            looping: true,
         }
     }
 
     pub fn renew(&mut self) {
+        self.name.clear();
         self.list = Vec::with_capacity(10);
         self.current = 0;
         self.status[1] = Status::default();
     }
 
+    // Slides of a song, in the verse order
+    fn slides(&self, song: Song) -> Song {
+        song.arranged().split_slides(self.max_lines)
+    }
+
+    // Replace the items, by a plan for example
+    pub fn replace(&mut self, name: String, items: Vec<Item>) {
+        self.renew();
+        self.name = name;
+        self.list = items
+            .into_iter()
+            .map(|item| match item.kind {
+                ItemKind::Song(song) => Item {
+                    kind: ItemKind::Song(self.slides(song)),
+                    ..item
+                },
+                _ => item,
+            })
+            .collect();
+    }
+
     pub fn add(&mut self, song: Option<Song>, content: Content) {
         match content {
-            Content::Preview => self.preview = song,
+            Content::Preview => self.preview = song.map(|song| self.slides(song)),
             Content::Direct => self.push_maybe(song),
         }
     }
 
     pub fn push_maybe(&mut self, song: Option<Song>) {
         if let Some(song) = song {
-            self.list.push(song);
+            self.list.push(Item::song(self.slides(song)));
+        }
+    }
+
+    pub fn push_placeholder(&mut self, slot: String) {
+        self.list.push(Item::placeholder(slot));
+    }
+
+    pub fn push_timer(&mut self) {
+        self.list.push(Item::timer(Timer::default()));
+    }
+
+    pub fn timer_mut(&mut self, index: usize) -> Option<&mut Timer> {
+        match self.list.get_mut(index) {
+            Some(Item {
+                kind: ItemKind::Timer(timer),
+                ..
+            }) => Some(timer),
+            _ => None,
+        }
+    }
+
+    pub fn push_media(&mut self, media: Media) {
+        self.list.push(Item::media(media));
+    }
+
+    pub fn media_mut(&mut self, index: usize) -> Option<&mut Media> {
+        match self.list.get_mut(index) {
+            Some(Item {
+                kind: ItemKind::Media(media),
+                ..
+            }) => Some(media),
+            _ => None,
+        }
+    }
+
+    // The items shown on the display, like a countdown
+    pub fn current_item(&self) -> Option<&Item> {
+        self.list.get(self.current)
+    }
+
+    // Interval of the live song when its loop plays, the other items have no slides
+    pub fn loop_interval(&self) -> Option<u32> {
+        let item = self.current_item()?;
+        item.as_song()?;
+        (self.looping && item.interval > 0).then_some(item.interval)
+    }
+
+    pub fn set_interval(&mut self, interval: u32) {
+        if let Some(item) = self.list.get_mut(self.current) {
+            item.interval = interval;
+            self.looping = true;
+        }
+    }
+
+    pub fn set_background(&mut self, index: usize, background: Option<String>) {
+        if let Some(item) = self.list.get_mut(index) {
+            item.background = background;
+        }
+    }
+
+    pub fn set_notes(&mut self, index: usize, notes: String) {
+        if let Some(item) = self.list.get_mut(index) {
+            item.notes = notes;
+        }
+    }
+
+    pub fn set_duration(&mut self, index: usize, duration: u32) {
+        if let Some(item) = self.list.get_mut(index) {
+            item.duration = duration;
+        }
+    }
+
+    // The timers need to be redrawn each second
+    pub fn has_timer(&self) -> bool {
+        self.list.iter().any(|item| item.as_timer().is_some())
+    }
+
+    // Insert a song after the current one, return its position
+    pub fn insert_next(&mut self, song: Option<Song>) -> Option<usize> {
+        let song = song?;
+        let index = match self.list.is_empty() {
+            true => 0,
+            false => self.current + 1,
+        };
+        self.list.insert(index, Item::song(self.slides(song)));
+        Some(index)
+    }
+
+    // Put a song in a placeholder, the slot keeps its name
+    pub fn fill(&mut self, index: usize, song: Option<Song>) {
+        let Some(song) = song.map(|song| self.slides(song)) else {
+            return;
+        };
+        if let Some(item) = self.list.get_mut(index) {
+            item.kind = ItemKind::Song(song);
+        }
+    }
+
+    // Freezing again goes back to the song
+    pub fn set_status(&mut self, content: Content, status: Status) {
+        let current = &mut self.status[content as usize];
+        *current = match (*current, status) {
+            (Status::Freeze, Status::Freeze) => Status::Song,
+            _ => status,
+        };
+        if *current == Status::Freeze {
+            self.frozen[content as usize] = self.current_song(content).cloned();
         }
     }
 
@@ -208,39 +643,47 @@ impl Service {
     }
 
     pub fn current_song_index(&self) -> Option<usize> {
-        if !self.list.is_empty() {
+        if !self.is_empty() {
             Some(self.current)
         } else {
             None
         }
     }
 
+    // A placeholder shows nothing
     pub fn current_song(&self, content: Content) -> Option<&Song> {
         match content {
-            Content::Direct => {
-                if !self.list.is_empty() {
-                    Some(&self.list[self.current])
-                } else {
-                    None
-                }
-            }
+            Content::Direct => self.list.get(self.current).and_then(Item::as_song),
             Content::Preview => self.preview.as_ref(),
         }
     }
 
     pub fn current_song_mut(&mut self, content: Content) -> Option<&mut Song> {
         match content {
-            Content::Direct => {
-                if !self.list.is_empty() {
-                    Some(&mut self.list[self.current])
-                } else {
-                    None
-                }
-            }
+            Content::Direct => match self.list.get_mut(self.current) {
+                Some(Item {
+                    kind: ItemKind::Song(song),
+                    ..
+                }) => Some(song),
+                _ => None,
+            },
             Content::Preview => self.preview.as_mut(),
         }
     }
 
+    pub fn len(&self) -> usize {
+        self.list.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
+    // Items of the service, without copying them
+    pub fn iter(&self) -> std::slice::Iter<'_, Item> {
+        self.list.iter()
+    }
+
     pub fn change(&mut self, content: Content, f: impl Fn(&mut Song)) -> Task<Message> {
         if let Some(song) = self.current_song_mut(content) {
             f(song);
@@ -251,13 +694,91 @@ impl Service {
     pub fn perform(&mut self, saction: SAction) -> Task<Message> {
         match saction {
             SAction::New => self.renew(),
-            // OpenLP service files are zip files what contains a service_data.osj file (in json)
-            SAction::Open => todo!(),
-            SAction::Save => todo!(),
+            SAction::Open => {
+                return Task::perform(
+                    rfd::AsyncFileDialog::new()
+                        .add_filter("Services", &[SERVICE_EXTENSION])
+                        .pick_file(),
+                    |file| file.map(|file| Message::OpenService(file.path().to_path_buf())),
+                )
+                .and_then(Task::done);
+            }
+            SAction::Save => {
+                let name = match self.name.trim() {
+                    "" => "Service",
+                    name => name,
+                };
+                return Task::perform(
+                    rfd::AsyncFileDialog::new()
+                        .set_file_name(format!("{}.{}", name, SERVICE_EXTENSION))
+                        .add_filter("Services", &[SERVICE_EXTENSION])
+                        .save_file(),
+                    |file| file.map(|file| Message::SaveService(file.path().to_path_buf())),
+                )
+                .and_then(Task::done);
+            }
         }
         Task::none()
     }
 
+    // State of the service saved regularly, the songs are reloaded from the library
+    pub fn session(&self) -> Session {
+        Session {
+            name: self.name.clone(),
+            items: self
+                .list
+                .iter()
+                .map(|item| SessionItem {
+                    slot: item.slot.clone(),
+                    id: item.as_song().map(|song| song.id),
+                    current: item.as_song().map_or(0, |song| song.current),
+                    transpose: item.as_song().map_or(0, |song| song.transpose),
+                    timer: item.as_timer().map(Timer::spec),
+                    media: item.as_media().map(Media::spec),
+                    interval: item.interval,
+                    background: item.background.clone(),
+                    notes: item.notes.clone(),
+                    duration: item.duration,
+                })
+                .collect(),
+            current: self.current,
+            status: self.status,
+            clean_exit: false,
+        }
+    }
+
+    pub fn restore(&mut self, db: &Connection, session: &Session) {
+        self.name = session.name.clone();
+        self.list = session
+            .items
+            .iter()
+            .filter_map(|saved| {
+                let kind = if let Some(timer) = saved.timer.as_deref().and_then(Timer::from_spec) {
+                    ItemKind::Timer(timer)
+                } else if let Some(media) = saved.media.as_deref().and_then(Media::from_spec) {
+                    ItemKind::Media(media)
+                } else if let Some(id) = saved.id {
+                    let mut song = self.slides(load_song(db, id).ok()?);
+                    song.set_current(saved.current);
+                    song.transpose = saved.transpose;
+                    ItemKind::Song(song)
+                } else {
+                    ItemKind::Placeholder
+                };
+                Some(Item {
+                    slot: saved.slot.clone(),
+                    kind,
+                    interval: saved.interval,
+                    background: saved.background.clone(),
+                    notes: saved.notes.clone(),
+                    duration: saved.duration,
+                })
+            })
+            .collect();
+        self.current = session.current.min(self.list.len().saturating_sub(1));
+        self.status = session.status;
+    }
+
     /*
     %USERPROFILE%\AppData\Local\RustLP\
     Compress-Archive file1 file2 archive.zip
@@ -266,13 +787,60 @@ impl Service {
 }
 
 impl IntoIterator for Service {
-    type IntoIter = std::vec::IntoIter<Song>;
-    type Item = Song;
+    type IntoIter = std::vec::IntoIter<Item>;
+    type Item = Item;
     fn into_iter(self) -> Self::IntoIter {
         self.list.into_iter()
     }
 }
 
+impl<'a> IntoIterator for &'a Service {
+    type IntoIter = std::slice::Iter<'a, Item>;
+    type Item = &'a Item;
+    fn into_iter(self) -> Self::IntoIter {
+        self.list.iter()
+    }
+}
+
+// The service files are the session in JSON, the songs are reloaded from the library
+const SERVICE_EXTENSION: &str = "rlp";
+
+pub fn save_service(path: &Path, session: &Session) -> std::io::Result<()> {
+    std::fs::write(path, serde_json::to_string_pretty(session)?)
+}
+
+pub fn load_service(path: &Path) -> std::io::Result<Session> {
+    Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+}
+
+// Autosave of the service, to recover it after a crash
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Session {
+    pub name: String,
+    pub items: Vec<SessionItem>,
+    pub current: usize,
+    pub status: [Status; 2],
+    // Set when the control window is closed
+    pub clean_exit: bool,
+}
+
+// A placeholder has no song
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionItem {
+    pub slot: Option<String>,
+    pub id: Option<u16>,
+    pub current: usize,
+    pub transpose: i8,
+    pub timer: Option<String>,
+    pub media: Option<String>,
+    pub interval: u32,
+    pub background: Option<String>,
+    pub notes: String,
+    pub duration: u32,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum SAction {
     New,
@@ -280,6 +848,13 @@ pub enum SAction {
     Save,
 }
 
+// Library actions
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LAction {
+    Import,
+    Export(Format),
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -287,8 +862,11 @@ mod test {
     fn test() {
         let db = connect_db().unwrap();
         let books = load_songbooks(&db).unwrap();
-        let mut query = db.prepare(Sort::QUERYS[0]).unwrap();
-        let mut iterator = query.query([""]).unwrap();
+        let sql = Sort::QUERYS[0].replace("{filter}", Filter::SQL);
+        let mut query = db.prepare(&sql).unwrap();
+        let mut iterator = query
+            .query(params!["", None::<u16>, None::<String>, None::<String>])
+            .unwrap();
         let mut j = 0;
         while let Ok(Some(i)) = iterator.next() {
             let id = i.get(0).unwrap();
@@ -10,46 +10,217 @@ use iced::{
     mouse,
     widget::{container, image, text},
 };
+use std::sync::LazyLock;
 
-use crate::{App, Message, control::Content, db::Status, widget::BOLD};
+use crate::{
+    App, Message,
+    backdrop::{self, Backdrop},
+    control::Content,
+    db::{Item, Status},
+    media::MediaKind,
+    song::Song,
+    widget::BOLD,
+};
 
+const OVERLAY_SIZE: f32 = 30.0;
 const DEFAULT_IMAGE: &[u8] = include_bytes!("../cross.jpg");
+// The handle hashes the image, it is only created once
+static DEFAULT_HANDLE: LazyLock<Handle> =
+    LazyLock::new(|| Handle::from_bytes(Bytes::from_static(DEFAULT_IMAGE)));
 
 impl App {
     pub fn view_display(&self, content: Content) -> Element<'_, Message> {
-        Display::new(self, content).into()
+        let status = self.service.status[content as usize];
+        let song = match status {
+            Status::Freeze => self.service.frozen[content as usize].as_ref(),
+            _ => self.service.current_song(content),
+        };
+        let timer = match content {
+            Content::Direct => self.service.current_item().and_then(Item::as_timer),
+            Content::Preview => None,
+        };
+        let video = match content {
+            Content::Direct => self
+                .service
+                .current_item()
+                .and_then(Item::as_media)
+                .is_some_and(|media| media.kind == MediaKind::Video),
+            Content::Preview => false,
+        };
+        // The background of the item replaces the one of the song
+        let item = match content {
+            Content::Direct => self
+                .service
+                .current_item()
+                .and_then(|item| item.background.as_deref()),
+            Content::Preview => None,
+        };
+        let display = match (song, timer, status) {
+            (Some(song), _, Status::Freeze) => Display::new(self, song, song.current, Status::Song),
+            (Some(song), _, _) => Display::new(self, song, song.current, status),
+            // The timer is drawn large like the lyrics
+            (None, Some(timer), _) => Display {
+                lyrics: timer.text(self.clock()),
+                font_size: 120.0,
+                ..Display::empty(self, status)
+            },
+            // The video fills the screen like the background
+            (None, None, Status::Song) if video => Display::empty(self, Status::Background),
+            (None, None, _) => Display::empty(self, status),
+        };
+        Display {
+            overlay: self.overlay().unwrap_or_default(),
+            ..display
+        }
+        .backdrop(self, &Backdrop::parse(item))
+        .into()
+    }
+
+    // Miniature of a slide, as it would be shown
+    pub fn view_slide(&self, song: &Song, slide: usize) -> Element<'_, Message> {
+        Display::new(self, song, slide, Status::Song).into()
     }
 }
 
 struct Display {
     resolution: Size,
     status: Status,
-    title: String,
+    footer: String,
     lyrics: String,
+    // Timer in the top right corner
+    overlay: String,
     font_size: f32,
     image: Handle,
+    // Solid color drawn instead of the image
+    color: Option<Color>,
 }
 
 impl Display {
-    fn new(app: &App, content: Content) -> Self {
-        let song = &app
-            .service
-            .current_song(content)
-            .cloned()
-            .unwrap_or_default();
-        let title = song.title(&app.books);
-        let lyrics = song.get(song.current);
+    fn empty(app: &App, status: Status) -> Self {
+        // The frames of a video replace the background
+        let image = match (status, app.frame()) {
+            (Status::Logo, _) if !app.set.logo.is_empty() => Handle::from_path(&app.set.logo),
+            (Status::Logo, _) => DEFAULT_HANDLE.clone(),
+            (_, Some(frame)) => frame,
+            (_, None) => DEFAULT_HANDLE.clone(),
+        };
         Self {
             resolution: app.resolution,
-            status: app.service.status[content as usize],
-            title: title,
-            lyrics: lyrics,
+            status: status,
+            footer: String::new(),
+            lyrics: String::new(),
+            overlay: String::new(),
             font_size: 40.0,
-            image: Handle::from_bytes(Bytes::from_static(DEFAULT_IMAGE)),
+            image: image,
+            color: None,
+        }
+    }
+
+    // Chosen for the song or the item, the logo and a video playing stay
+    fn backdrop(self, app: &App, backdrop: &Backdrop) -> Self {
+        if self.status == Status::Logo || app.frame().is_some() {
+            return self;
+        }
+        match backdrop {
+            Backdrop::Default => self,
+            Backdrop::Color(rgb) => Self {
+                color: Some(backdrop::color(*rgb)),
+                ..self
+            },
+            Backdrop::Image(name) => Self {
+                image: Handle::from_path(backdrop::path(name)),
+                color: None,
+                ..self
+            },
+        }
+    }
+
+    fn new(app: &App, song: &Song, slide: usize, status: Status) -> Self {
+        let footer = if app
+            .set
+            .footer_visibility
+            .is_visible(slide, song.lyrics.len())
+        {
+            footer(
+                &app.set.footer,
+                &[
+                    ("title", song.title(&app.books)),
+                    ("authors", song.authors.join(", ")),
+                    ("copyright", song.copyright.clone().unwrap_or_default()),
+                    ("ccli", song.ccli_number.clone().unwrap_or_default()),
+                    ("licence", app.set.ccli_licence.clone()),
+                ],
+            )
+        } else {
+            String::new()
+        };
+        Self {
+            footer: footer,
+            lyrics: song.get(slide),
+            ..Self::empty(app, status)
+        }
+        .backdrop(app, &Backdrop::parse(song.background.as_deref()))
+    }
+
+    fn draw_backdrop<Renderer>(
+        &self,
+        renderer: &mut Renderer,
+        layout: Layout<'_>,
+        viewport: &iced::Rectangle,
+    ) where
+        Renderer:
+            iced::advanced::Renderer + iced::advanced::image::Renderer<Handle = image::Handle>,
+    {
+        match self.color {
+            Some(color) => container::draw_background(
+                renderer,
+                &container::Style {
+                    background: Some(Background::Color(color)),
+                    ..Default::default()
+                },
+                layout.bounds(),
+            ),
+            None => image::draw(
+                renderer,
+                layout,
+                viewport,
+                &self.image,
+                iced::ContentFit::Contain,
+                image::FilterMethod::Linear,
+                iced::Rotation::default(),
+                1.0,
+                1.0,
+            ),
         }
     }
 }
 
+// Fill the footer template, the text before an empty field is removed with it:
+// "{title} — {authors}" gives "Title" for a song without authors
+fn footer(template: &str, fields: &[(&str, String)]) -> String {
+    let mut footer = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let Some(end) = rest[start..].find('}').map(|end| start + end) else {
+            break;
+        };
+        match fields
+            .iter()
+            .find(|(name, _)| *name == &rest[start + 1..end])
+        {
+            Some((_, value)) if value.is_empty() => (),
+            Some((_, value)) => {
+                footer += &rest[..start];
+                footer += value;
+            }
+            None => footer += &rest[..=end],
+        }
+        rest = &rest[end + 1..];
+    }
+    footer += rest;
+    footer.trim().to_string()
+}
+
 impl<Message, Renderer> Widget<Message, Theme, Renderer> for Display
 where
     Renderer: iced::advanced::Renderer
@@ -105,25 +276,29 @@ where
                     bounds,
                 );
             }
-            Status::Song => {
-                // Background
-                image::draw(
-                    renderer,
-                    layout,
-                    viewport,
-                    &self.image,
-                    iced::ContentFit::Contain,
-                    image::FilterMethod::Linear,
-                    iced::Rotation::default(),
-                    1.0,
-                    1.0,
-                );
+            // The logo is centered on black, the background fills the screen
+            Status::Logo | Status::Background => {
+                if self.status == Status::Logo {
+                    container::draw_background(
+                        renderer,
+                        &container::Style {
+                            background: Some(Background::Color(Color::BLACK)),
+                            ..Default::default()
+                        },
+                        layout.bounds(),
+                    );
+                }
+                self.draw_backdrop(renderer, layout, viewport);
+            }
+            // The frozen song is drawn as a song
+            Status::Song | Status::Freeze => {
+                self.draw_backdrop(renderer, layout, viewport);
                 let bounds = layout.bounds();
                 let scale_factor = bounds.width / self.resolution.width;
-                // Title
+                // Footer
                 renderer.fill_text(
                     Text {
-                        content: self.title.clone(),
+                        content: self.footer.clone(),
                         bounds: bounds.size(),
                         size: (self.font_size * scale_factor / 2.0).into(),
                         line_height: text::LineHeight::default(),
@@ -162,6 +337,30 @@ where
                 renderer.fill_text(lyrics, bounds.center(), Color::WHITE, *viewport);
             }
         }
+        // The overlay stays over every screen
+        if !self.overlay.is_empty() {
+            let bounds = layout.bounds();
+            let scale_factor = bounds.width / self.resolution.width;
+            renderer.fill_text(
+                Text {
+                    content: self.overlay.clone(),
+                    bounds: bounds.size(),
+                    size: (OVERLAY_SIZE * scale_factor).into(),
+                    line_height: text::LineHeight::default(),
+                    font: BOLD,
+                    align_x: text::Alignment::Right,
+                    align_y: Vertical::Top,
+                    shaping: text::Shaping::default(),
+                    wrapping: text::Wrapping::None,
+                },
+                iced::Point {
+                    x: bounds.x + bounds.width,
+                    y: bounds.y,
+                },
+                Color::WHITE,
+                *viewport,
+            );
+        }
     }
 }
 
@@ -175,3 +374,27 @@ where
         Self::new(widget)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn footer_template() {
+        let template = "{title} — {authors} © {copyright} CCLI {licence}";
+        let fields = [
+            ("title", String::from("Amazing Grace")),
+            ("authors", String::new()),
+            ("copyright", String::from("Public Domain")),
+            ("licence", String::from("123456")),
+        ];
+        assert_eq!(
+            footer(template, &fields),
+            "Amazing Grace © Public Domain CCLI 123456"
+        );
+        assert_eq!(
+            footer("{title} {unknown}", &fields),
+            "Amazing Grace {unknown}"
+        );
+    }
+}
@@ -0,0 +1,185 @@
+use iced::{
+    Element, Length, Task,
+    widget::{Column, Row, button, column, horizontal_space, pick_list, row, text_input},
+    window,
+};
+
+use crate::{
+    App, Message,
+    db::Entry,
+    library::{self, Record},
+    style,
+    widget::ttext,
+};
+
+// Song editor: metadata and topics, the lyrics come from the import
+#[derive(Debug, Default)]
+pub struct Editor {
+    id: u16,
+    record: Record,
+    topic: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EAction {
+    Title(String),
+    AlternateTitle(String),
+    Copyright(String),
+    Ccli(String),
+    VerseOrder(String),
+    TopicChanged(String),
+    AddTopic(String),
+    RemoveTopic(usize),
+    Background(Option<String>),
+    Save,
+}
+
+fn optional(value: String) -> Option<String> {
+    (!value.trim().is_empty()).then_some(value)
+}
+
+impl App {
+    pub fn open_editor(&mut self) -> Task<Message> {
+        match library::load_record(&self.db, self.db_select) {
+            Ok(record) => {
+                self.editor = Editor {
+                    id: self.db_select,
+                    record,
+                    topic: String::new(),
+                };
+                crate::WId::open(&mut self.window.editor)
+            }
+            Err(e) => {
+                println!("ERROR: Failed to load song {}: {}", self.db_select, e);
+                Task::none()
+            }
+        }
+    }
+
+    pub fn edit(&mut self, eaction: EAction) -> Task<Message> {
+        let song = &mut self.editor.record.song;
+        match eaction {
+            EAction::Title(title) => song.title = title,
+            EAction::AlternateTitle(title) => song.alternate_title = optional(title),
+            EAction::Copyright(copyright) => song.copyright = optional(copyright),
+            EAction::Ccli(ccli) => song.ccli_number = optional(ccli),
+            EAction::VerseOrder(order) => song.verse_order = optional(order),
+            EAction::TopicChanged(topic) => self.editor.topic = topic,
+            EAction::AddTopic(topic) => {
+                let topic = topic.trim().to_string();
+                let topics = &mut self.editor.record.topics;
+                if !topic.is_empty() && !topics.contains(&topic) {
+                    topics.push(topic);
+                }
+                self.editor.topic.clear();
+            }
+            EAction::RemoveTopic(index) => {
+                self.editor.record.topics.remove(index);
+            }
+            EAction::Background(background) => song.background = background,
+            EAction::Save => {
+                let Editor { id, record, .. } = &self.editor;
+                let result = library::update_song(&self.db, *id, &record.song, &record.authors)
+                    .and_then(|_| library::set_topics(&self.db, *id, &record.topics))
+                    .and_then(|_| {
+                        library::update_background(&self.db, *id, record.song.background.as_deref())
+                    });
+                if let Err(e) = result {
+                    println!("ERROR: Failed to save song {}: {}", id, e);
+                    return Task::none();
+                }
+                self.reload_entries();
+                return match self.window.editor.take() {
+                    Some(editor) => window::close(editor),
+                    None => Task::none(),
+                };
+            }
+        }
+        Task::none()
+    }
+
+    pub fn view_editor(&self) -> Element<'_, Message> {
+        let song = &self.editor.record.song;
+        let topics = Row::with_children(self.editor.record.topics.iter().enumerate().map(
+            |(index, topic)| {
+                button(ttext(format!("{} ×", topic), self))
+                    .style(button::secondary)
+                    .on_press(Message::EditorAction(EAction::RemoveTopic(index)))
+                    .into()
+            },
+        ))
+        .spacing(self.set.spacing);
+        let known: Vec<Entry> = self
+            .topics
+            .iter()
+            .filter(|entry| !self.editor.record.topics.contains(&entry.name))
+            .cloned()
+            .collect();
+        column![
+            self.field("Title", &song.title, EAction::Title),
+            self.field(
+                "Alternate title",
+                song.alternate_title.as_deref().unwrap_or_default(),
+                EAction::AlternateTitle
+            ),
+            self.field(
+                "Copyright",
+                song.copyright.as_deref().unwrap_or_default(),
+                EAction::Copyright
+            ),
+            self.field(
+                "CCLI number",
+                song.ccli_number.as_deref().unwrap_or_default(),
+                EAction::Ccli
+            ),
+            self.field(
+                "Verse order",
+                song.verse_order.as_deref().unwrap_or_default(),
+                EAction::VerseOrder
+            ),
+            ttext("Topics", self),
+            topics,
+            row![
+                text_input("New topic", &self.editor.topic)
+                    .on_input(|topic| Message::EditorAction(EAction::TopicChanged(topic)))
+                    .on_submit(Message::EditorAction(EAction::AddTopic(
+                        self.editor.topic.clone()
+                    )))
+                    .size(self.set.font_size),
+                pick_list(known, None::<Entry>, |entry| {
+                    Message::EditorAction(EAction::AddTopic(entry.name))
+                })
+                .placeholder("Add a topic")
+                .text_size(self.set.font_size)
+                .style(style::theme_pick_list),
+            ]
+            .spacing(self.set.spacing),
+            ttext("Background", self),
+            self.view_backdrops(song.background.as_deref(), |background| {
+                Message::EditorAction(EAction::Background(background))
+            }),
+            row![
+                horizontal_space(),
+                button(ttext("Save", self)).on_press(Message::EditorAction(EAction::Save))
+            ],
+        ]
+        .spacing(self.set.spacing * 2.0)
+        .padding(5)
+        .width(Length::Fill)
+        .into()
+    }
+
+    fn field<'a>(
+        &'a self,
+        name: &'static str,
+        value: &'a str,
+        action: fn(String) -> EAction,
+    ) -> Column<'a, Message> {
+        column![
+            ttext(name, self),
+            text_input(name, value)
+                .on_input(move |value| Message::EditorAction(action(value)))
+                .size(self.set.font_size),
+        ]
+    }
+}
@@ -0,0 +1,407 @@
+// Read and write songs of the library, shared with the converter
+use rusqlite::{Connection, OptionalExtension, Result, params};
+use std::{
+    collections::HashMap,
+    hash::{DefaultHasher, Hash, Hasher},
+    path::Path,
+};
+
+use crate::{
+    chordpro, openlyrics, plaintext,
+    song::{Song, Verse},
+};
+
+// A song with the names of its authors, songbook and topics, as written in a file
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Record {
+    pub song: Song,
+    pub authors: Vec<String>,
+    pub book: Option<String>,
+    pub topics: Vec<String>,
+}
+
+impl Record {
+    pub fn new((song, authors): (Song, Vec<String>)) -> Self {
+        Record {
+            song,
+            authors,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    ChordPro,
+    OpenLyrics,
+    Text,
+}
+
+impl Format {
+    pub const ALL: [Format; 3] = [Format::ChordPro, Format::OpenLyrics, Format::Text];
+
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+        Format::ALL
+            .into_iter()
+            .find(|format| format.extensions().contains(&extension.as_str()))
+    }
+
+    pub fn extensions(&self) -> &'static [&'static str] {
+        match self {
+            Format::ChordPro => &["cho", "chopro", "chordpro", "crd", "pro"],
+            Format::OpenLyrics => &["xml"],
+            Format::Text => &["txt"],
+        }
+    }
+
+    pub fn parse(&self, input: &str) -> std::result::Result<Record, String> {
+        match self {
+            Format::ChordPro => Ok(Record::new(chordpro::parse(input))),
+            Format::OpenLyrics => openlyrics::parse(input).map_err(|e| e.to_string()),
+            Format::Text => Ok(Record::new(plaintext::parse(input))),
+        }
+    }
+
+    pub fn export(&self, record: &Record) -> String {
+        match self {
+            Format::ChordPro => chordpro::export(&record.song, &record.authors),
+            Format::OpenLyrics => openlyrics::export(record),
+            Format::Text => plaintext::export(&record.song, &record.authors),
+        }
+    }
+}
+
+impl std::fmt::Display for Format {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+pub fn load_song(db: &Connection, id: u16) -> Result<Song> {
+    let mut query = db.prepare(
+        "SELECT id, title, lyrics, book, number, alternate_title, comments,
+                verse_order, copyright, ccli_number, background
+            FROM songs WHERE id = ?;",
+    )?;
+    let mut song: Song = query.query_one([id], |row| row.try_into())?;
+    song.authors = song_authors(db, id)?;
+    Ok(song)
+}
+
+pub fn song_authors(db: &Connection, song: u16) -> Result<Vec<String>> {
+    let mut query = db.prepare(
+        "SELECT a.name FROM authors a
+            JOIN authors_songs asng ON a.id = asng.author_id
+            WHERE asng.song_id = ?;",
+    )?;
+    query.query_map([song], |row| row.get(0))?.collect()
+}
+
+pub fn song_topics(db: &Connection, song: u16) -> Result<Vec<String>> {
+    let mut query = db.prepare(
+        "SELECT t.name FROM topics t
+            JOIN songs_topics st ON t.id = st.topic_id
+            WHERE st.song_id = ?;",
+    )?;
+    query.query_map([song], |row| row.get(0))?.collect()
+}
+
+pub fn load_record(db: &Connection, id: u16) -> Result<Record> {
+    let song = load_song(db, id)?;
+    let book = match song.book {
+        Some(book) => db
+            .query_row("SELECT name FROM books WHERE id = ?;", [book], |row| {
+                row.get(0)
+            })
+            .optional()?,
+        None => None,
+    };
+    Ok(Record {
+        authors: song.authors.clone(),
+        topics: song_topics(db, id)?,
+        book,
+        song,
+    })
+}
+
+// Return the id of the author, create it if needed
+pub fn author_id(db: &Connection, name: &str) -> Result<u16> {
+    named_id(db, "authors", name)
+}
+
+pub fn book_id(db: &Connection, name: &str) -> Result<u16> {
+    named_id(db, "books", name)
+}
+
+pub fn topic_id(db: &Connection, name: &str) -> Result<u16> {
+    named_id(db, "topics", name)
+}
+
+fn named_id(db: &Connection, table: &str, name: &str) -> Result<u16> {
+    let id = db
+        .query_row(
+            &format!("SELECT id FROM {} WHERE name = ?;", table),
+            [name],
+            |row| row.get(0),
+        )
+        .optional()?;
+    match id {
+        Some(id) => Ok(id),
+        None => {
+            db.execute(&format!("INSERT INTO {} (name) VALUES (?);", table), [name])?;
+            Ok(db.last_insert_rowid() as u16)
+        }
+    }
+}
+
+pub fn add_topic(db: &Connection, song: u16, topic: &str) -> Result<()> {
+    db.execute(
+        "INSERT INTO songs_topics (song_id, topic_id) VALUES (?, ?);",
+        [song, topic_id(db, topic)?],
+    )?;
+    Ok(())
+}
+
+pub fn set_topics(db: &Connection, song: u16, topics: &[String]) -> Result<()> {
+    db.execute("DELETE FROM songs_topics WHERE song_id = ?;", [song])?;
+    for topic in topics {
+        add_topic(db, song, topic)?;
+    }
+    Ok(())
+}
+
+pub fn set_authors(db: &Connection, song: u16, authors: &[String]) -> Result<()> {
+    db.execute("DELETE FROM authors_songs WHERE song_id = ?;", [song])?;
+    for author in authors {
+        db.execute(
+            "INSERT INTO authors_songs (author_id, song_id) VALUES (?, ?);",
+            [author_id(db, author)?, song],
+        )?;
+    }
+    Ok(())
+}
+
+pub fn insert_song(db: &Connection, song: &Song, authors: &[String]) -> Result<u16> {
+    db.execute(
+        "INSERT INTO songs (title, lyrics, book, number, alternate_title, comments,
+                verse_order, copyright, ccli_number, background)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?);",
+        params![
+            song.title,
+            song.lyrics_xml(),
+            song.book,
+            song.number,
+            song.alternate_title,
+            song.comments,
+            song.verse_order,
+            song.copyright,
+            song.ccli_number,
+            song.background
+        ],
+    )?;
+    let id = db.last_insert_rowid() as u16;
+    set_authors(db, id, authors)?;
+    Ok(id)
+}
+
+pub fn update_song(db: &Connection, id: u16, song: &Song, authors: &[String]) -> Result<()> {
+    db.execute(
+        "UPDATE songs SET title = ?, lyrics = ?, book = ?, number = ?, alternate_title = ?,
+                comments = ?, verse_order = ?, copyright = ?, ccli_number = ?
+            WHERE id = ?;",
+        params![
+            song.title,
+            song.lyrics_xml(),
+            song.book,
+            song.number,
+            song.alternate_title,
+            song.comments,
+            song.verse_order,
+            song.copyright,
+            song.ccli_number,
+            id
+        ],
+    )?;
+    set_authors(db, id, authors)
+}
+
+// Kept apart so that updating a song from a file keeps its background
+pub fn update_background(db: &Connection, id: u16, background: Option<&str>) -> Result<()> {
+    db.execute(
+        "UPDATE songs SET background = ? WHERE id = ?;",
+        params![background, id],
+    )?;
+    Ok(())
+}
+
+// What to do with a song already in the library
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Policy {
+    #[default]
+    Skip,
+    Update,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Report {
+    pub inserted: usize,
+    pub updated: usize,
+    pub skipped: usize,
+    pub failed: usize,
+}
+
+impl std::fmt::Display for Report {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} inserted, {} updated, {} skipped, {} failed",
+            self.inserted, self.updated, self.skipped, self.failed
+        )
+    }
+}
+
+// Import songs without duplicates: a song is already known if it comes from the same
+// source (file, OpenLP id) or if it has the same title and lyrics
+pub struct Importer<'a> {
+    db: &'a Connection,
+    policy: Policy,
+    sources: HashMap<String, u16>,
+    songs: HashMap<(String, u64), u16>,
+    pub report: Report,
+}
+
+impl<'a> Importer<'a> {
+    pub fn new(db: &'a Connection, policy: Policy) -> Result<Self> {
+        let mut importer = Importer {
+            db,
+            policy,
+            sources: HashMap::new(),
+            songs: HashMap::new(),
+            report: Report::default(),
+        };
+        let mut query = db.prepare("SELECT id, title, lyrics, source FROM songs;")?;
+        let mut rows = query.query([])?;
+        while let Some(row) = rows.next()? {
+            let id = row.get(0)?;
+            let title = row.get::<_, Option<String>>(1)?.unwrap_or_default();
+            let lyrics = Song::parse_lyrics(row.get(2)?).unwrap_or_default();
+            importer.songs.insert((title, hash(&lyrics)), id);
+            if let Some(source) = row.get(3)? {
+                importer.sources.insert(source, id);
+            }
+        }
+        Ok(importer)
+    }
+
+    // Return the id of the song if it was inserted or updated, the topics are only
+    // replaced if the record has some
+    pub fn import(&mut self, record: &Record, source: Option<&str>) -> Option<u16> {
+        let Record {
+            song,
+            authors,
+            book,
+            topics,
+        } = record;
+        let key = (song.title.clone(), hash(&song.lyrics));
+        let known = source
+            .and_then(|source| self.sources.get(source))
+            .or_else(|| self.songs.get(&key))
+            .copied();
+        if known.is_some() && self.policy == Policy::Skip {
+            self.report.skipped += 1;
+            return None;
+        }
+        let result = self.db.execute_batch("SAVEPOINT song;").and_then(|_| {
+            let mut song = song.clone();
+            if let Some(book) = book {
+                song.book = Some(book_id(self.db, book)?);
+            }
+            let id = match known {
+                Some(id) => update_song(self.db, id, &song, authors).map(|_| id)?,
+                None => insert_song(self.db, &song, authors)?,
+            };
+            if !topics.is_empty() {
+                set_topics(self.db, id, topics)?;
+            }
+            self.db.execute(
+                "UPDATE songs SET source = ? WHERE id = ?;",
+                params![source, id],
+            )?;
+            self.db.execute_batch("RELEASE song;")?;
+            Ok(id)
+        });
+        match result {
+            Ok(id) => {
+                match known {
+                    Some(_) => self.report.updated += 1,
+                    None => self.report.inserted += 1,
+                }
+                if let Some(source) = source {
+                    self.sources.insert(source.to_string(), id);
+                }
+                self.songs.insert(key, id);
+                Some(id)
+            }
+            Err(e) => {
+                println!("ERROR: Failed to import \"{}\": {}", song.title, e);
+                self.db
+                    .execute_batch("ROLLBACK TO song; RELEASE song;")
+                    .ok();
+                self.report.failed += 1;
+                None
+            }
+        }
+    }
+
+    pub fn import_file(&mut self, path: &Path) -> Option<u16> {
+        let source = std::fs::canonicalize(path).unwrap_or(path.to_path_buf());
+        let input = Format::from_path(path)
+            .ok_or(String::from("Unknown format"))
+            .and_then(|format| {
+                let input = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+                format.parse(&input)
+            });
+        match input {
+            Ok(record) => self.import(&record, Some(&source.to_string_lossy())),
+            Err(e) => {
+                println!("ERROR: Failed to import {}: {}", path.display(), e);
+                self.report.failed += 1;
+                None
+            }
+        }
+    }
+}
+
+fn hash(lyrics: &[(Verse, String)]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for (verse, text) in lyrics {
+        verse.hash(&mut hasher);
+        text.trim().hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+// Write a song in the directory, the file is named after the song title
+pub fn export_song(
+    db: &Connection,
+    id: u16,
+    format: Format,
+    directory: &Path,
+) -> std::result::Result<(), String> {
+    let record = load_record(db, id).map_err(|e| e.to_string())?;
+    let song = &record.song;
+    let name: String = song
+        .title
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == ' ' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let path = directory.join(format!("{} ({}).{}", name, song.id, format.extensions()[0]));
+    std::fs::write(path, format.export(&record)).map_err(|e| e.to_string())
+}
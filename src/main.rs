@@ -1,23 +1,55 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use iced::{Element, Size, Task, Theme, widget::container, window};
+use iced::{
+    Element, Size, Task, Theme,
+    widget::{container, scrollable, text_editor},
+    window,
+};
 use rusqlite::Connection;
+use std::{
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
 use crate::{
-    control::Content,
-    db::{SAction, Service, Status, load_index, load_song},
+    control::{Content, Interval, Move, Pane},
+    db::{Entry, Filter, LAction, SAction, Service, Session, Status, load_index},
+    library::{Format, load_song},
     song::{Book, Song},
 };
 
+mod backdrop;
+mod chordpro;
+mod chords;
 mod control;
 mod db;
 mod display;
+mod editor;
+mod library;
+mod manage;
+mod media;
+mod openlyrics;
+mod paste;
+mod plaintext;
+mod plan;
+mod print;
+mod schema;
 mod settings;
 mod song;
+mod stage;
 mod style;
+mod timer;
+mod timing;
+mod usage;
 mod widget;
 
 const NAME: &str = "RustLP";
+// Time without typing before searching
+const SEARCH_DELAY: Duration = Duration::from_millis(150);
+// The service is saved in the config directory, next to the settings
+const AUTOSAVE: &str = "autosave";
+const AUTOSAVE_DELAY: Duration = Duration::from_secs(10);
 
 fn main() -> iced::Result {
     iced::daemon(App::new, App::update, App::view)
@@ -35,11 +67,43 @@ struct App {
     set: settings::Settings,
     db: Connection,
     db_select: u16,
+    pane: Pane,
     sort: db::Sort,
     index: Vec<(u16, String)>,
+    // Offset and height of the visible part of the library
+    index_scroll: (f32, f32),
+    // Connection of the searches run in the background
+    search_db: Arc<Mutex<Connection>>,
+    search_generation: u64,
     service: Service,
+    // Last state saved, and the session of a crash not restored yet
+    autosaved: Session,
+    recovery: Option<Session>,
     books: Vec<Book>,
     search: String,
+    filter: Filter,
+    topics: Vec<Entry>,
+    paste: text_editor::Content,
+    manage: manage::Manage,
+    editor: editor::Editor,
+    usage: usage::UsageReport,
+    plans: plan::Plans,
+    // Local time minus UTC, in seconds
+    utc_offset: i64,
+    // Time of the timer being typed
+    timer_input: Option<String>,
+    // Video or audio playing, and the position being dragged
+    player: Option<media::Player>,
+    seek: Option<f32>,
+    // Last failure of ffmpeg, shown with the media controls
+    media_error: Option<String>,
+    // Images of the backgrounds folder, and the item choosing one
+    backdrops: Vec<String>,
+    backdrop_item: Option<usize>,
+    // Notes of every item editable in the service
+    show_notes: bool,
+    timing: timing::Timing,
+    timings: timing::TimingReport,
 }
 
 #[derive(Debug, Clone)]
@@ -49,11 +113,65 @@ enum Message {
     Close(window::Id),
     GoSearch,
     SearchChanged(String),
+    Search(u64),
+    IndexLoaded(u64, Result<Vec<(u16, String)>, String>),
+    IndexScrolled(scrollable::Viewport),
     ExitSearch,
     SortChanged(db::Sort),
+    TopicChanged(Option<Entry>),
+    UsageChanged(db::Usage),
     SelectSong(u16),
     OpenSong(u16, Content),
+    // Keyboard
+    FocusPane(Pane),
+    Navigate(Move),
+    PreviewSelected,
+    GoLiveSelected,
+    InsertSelected,
+    LibraryAction(LAction),
+    ImportFiles(Vec<PathBuf>),
+    ExportSongs(Format, PathBuf),
+    OpenPaste,
+    PasteEdited(text_editor::Action),
+    PasteImport,
+    OpenManage,
+    ManageAction(manage::MAction),
+    OpenEditor,
+    EditorAction(editor::EAction),
+    OpenUsage,
+    UsageAction(usage::UAction),
+    ExportUsage(PathBuf),
+    OpenPlans,
+    PlanAction(plan::PAction),
+    FillPlaceholder(usize),
+    AddTimer,
+    TimerAction(usize, timer::TAction),
+    Tick,
+    PickMedia,
+    AddMedia(PathBuf),
+    MediaAction(usize, media::VAction),
+    MediaProbed(usize, PathBuf, Result<media::Probe, String>),
+    MediaFrame,
+    PickBackdrops,
+    ImportBackdrops(Vec<PathBuf>),
+    ToggleBackdrops(usize),
+    ItemBackdrop(usize, Option<String>),
+    ToggleNotes,
+    ItemNotes(usize, String),
+    DurationChanged(usize, String),
+    ApplyDuration,
+    EndService,
+    OpenTimings,
+    TimingAction(timing::RAction),
+    Print(print::Layout),
+    ExportPrint(print::Layout, PathBuf),
     ServiceAction(SAction),
+    OpenService(PathBuf),
+    SaveService(PathBuf),
+    Autosave,
+    RestoreSession,
+    DismissSession,
+    ServiceNameChanged(String),
     AddToService,
     ChangeCurrentSong(usize),
     ChangeScreen(Status, Content),
@@ -62,10 +180,25 @@ enum Message {
     Next(Content),
     NextChorus(Content),
     NextVerse(Content),
+    LoopIntervalChanged(Interval),
+    LoopToggle,
+    LoopNext,
+    // Stage
+    OpenStage,
+    Transpose(i8),
+    CapoChanged(u8),
     // Settings
     OpenSettings,
     SpacingChanged(f32),
     TextFontChanged(f32),
+    FooterChanged(String),
+    FooterVisibilityChanged(settings::FooterVisibility),
+    LicenceChanged(String),
+    MaxLinesChanged(u8),
+    SlideViewChanged(settings::SlideView),
+    LogoChanged(String),
+    PickLogo,
+    SplitSlides,
     ThemeSelected,
     DebugToggle,
 }
@@ -83,27 +216,80 @@ impl App {
             exit_on_close_request: false,
             ..Default::default()
         });
-        let settings = confy::load(NAME, None).expect("ERROR: Failed to load settings");
+        let settings: settings::Settings =
+            confy::load(NAME, None).expect("ERROR: Failed to load settings");
+        let max_lines = settings.max_lines as usize;
         let db = db::connect_db().expect("ERROR: Failed to connect database");
+        let search_db = db::connect_db().expect("ERROR: Failed to connect database");
         let books = db::load_songbooks(&db).expect("ERROR: Failed to load books");
-        let index = load_index(&db, db::Sort::default(), &String::new())
+        let index = load_index(&db, db::Sort::default(), &String::new(), &Filter::default())
             .expect("ERROR: Failed to load index");
+        let topics = db::load_entries(&db, db::Kind::Topics).expect("ERROR: Failed to load topics");
+        let upcoming = plan::load_upcoming(&db).expect("ERROR: Failed to load the plans");
+        // There is no time zone in std, SQLite knows it
+        let utc_offset = db
+            .query_row(
+                "SELECT strftime('%s', 'now', 'localtime') - strftime('%s', 'now');",
+                [],
+                |row| row.get(0),
+            )
+            .expect("ERROR: Failed to read the time zone");
+        let recovery = confy::load::<Session>(NAME, AUTOSAVE)
+            .inspect_err(|e| println!("ERROR: Failed to load the autosave: {}", e))
+            .ok()
+            .filter(|session| !session.clean_exit && !session.items.is_empty());
         (
             Self {
                 window: WId {
                     control: control_id,
                     display: display_id,
                     settings: None,
+                    stage: None,
+                    paste: None,
+                    manage: None,
+                    editor: None,
+                    usage: None,
+                    plans: None,
+                    timings: None,
                 },
                 resolution: Size::new(1920.0, 1080.0), // Tempopary value
                 set: settings,
                 db: db,
                 db_select: 0,
+                pane: Pane::default(),
                 sort: db::Sort::default(),
                 index: index,
-                service: Service::new(),
+                index_scroll: (0.0, 0.0),
+                search_db: Arc::new(Mutex::new(search_db)),
+                search_generation: 0,
+                service: Service {
+                    max_lines: max_lines,
+                    ..Service::new()
+                },
+                autosaved: Session::default(),
+                recovery: recovery,
                 books: books,
                 search: String::new(),
+                filter: Filter::default(),
+                topics: topics,
+                paste: text_editor::Content::new(),
+                manage: manage::Manage::default(),
+                editor: editor::Editor::default(),
+                usage: usage::UsageReport::default(),
+                plans: plan::Plans {
+                    upcoming: upcoming,
+                    ..Default::default()
+                },
+                utc_offset: utc_offset,
+                timer_input: None,
+                player: None,
+                seek: None,
+                media_error: None,
+                backdrops: backdrop::load_library(),
+                backdrop_item: None,
+                show_notes: false,
+                timing: timing::Timing::default(),
+                timings: timing::TimingReport::default(),
             },
             Task::batch([
                 control.map(Message::WindowOpened),
@@ -125,15 +311,48 @@ impl App {
                     match key.as_ref() {
                         Key::Character("c") => Some(Message::NextChorus(Content::Direct)),
                         Key::Character("v") => Some(Message::NextVerse(Content::Direct)),
+                        // Screens of the display
+                        Key::Character("b") => {
+                            Some(Message::ChangeScreen(Status::DarkScreen, Content::Direct))
+                        }
+                        Key::Character("w") => {
+                            Some(Message::ChangeScreen(Status::WhiteScreen, Content::Direct))
+                        }
+                        Key::Character("s") => {
+                            Some(Message::ChangeScreen(Status::Song, Content::Direct))
+                        }
+                        Key::Character("l") => {
+                            Some(Message::ChangeScreen(Status::Logo, Content::Direct))
+                        }
+                        Key::Character("f") => {
+                            Some(Message::ChangeScreen(Status::Freeze, Content::Direct))
+                        }
+                        Key::Character("t") => {
+                            Some(Message::ChangeScreen(Status::Background, Content::Direct))
+                        }
                         Key::Named(Named::Enter) => Some(Message::AddToService),
-                        Key::Named(Named::ArrowUp) => Some(Message::Previous(Content::Direct)),
-                        Key::Named(Named::ArrowDown) => Some(Message::Next(Content::Direct)),
+                        Key::Named(Named::Insert) => Some(Message::InsertSelected),
+                        Key::Named(Named::Escape) => Some(Message::FocusPane(Pane::Live)),
+                        Key::Named(Named::ArrowUp) => Some(Message::Navigate(Move::Up)),
+                        Key::Named(Named::ArrowDown) => Some(Message::Navigate(Move::Down)),
+                        Key::Named(Named::PageUp) => Some(Message::Navigate(Move::PageUp)),
+                        Key::Named(Named::PageDown) => Some(Message::Navigate(Move::PageDown)),
+                        Key::Named(Named::Home) => Some(Message::Navigate(Move::Home)),
+                        Key::Named(Named::End) => Some(Message::Navigate(Move::End)),
                         _ => None,
                     }
                 } else if modifiers.command() {
                     match key.as_ref() {
                         Key::Character(",") => Some(Message::OpenSettings),
+                        Key::Character("m") => Some(Message::OpenStage),
                         Key::Character("f") => Some(Message::GoSearch),
+                        Key::Character("l") => Some(Message::FocusPane(Pane::Library)),
+                        Key::Named(Named::Enter) => Some(Message::GoLiveSelected),
+                        _ => None,
+                    }
+                } else if modifiers.shift() {
+                    match key.as_ref() {
+                        Key::Named(Named::Enter) => Some(Message::PreviewSelected),
                         _ => None,
                     }
                 } else {
@@ -141,6 +360,24 @@ impl App {
                 }
             }),
             window::close_events().map(Message::Close),
+            iced::time::every(AUTOSAVE_DELAY).map(|_| Message::Autosave),
+            // The timers and the clock of the service are redrawn each second
+            if self.service.has_timer() || self.service_running() {
+                iced::time::every(Duration::from_secs(1)).map(|_| Message::Tick)
+            } else {
+                iced::Subscription::none()
+            },
+            // Frames of the video, or the position of the sound
+            match self.player.as_ref().filter(|player| player.is_playing()) {
+                Some(player) => iced::time::every(player.delay()).map(|_| Message::MediaFrame),
+                None => iced::Subscription::none(),
+            },
+            // The live item loops over its slides
+            match self.service.loop_interval() {
+                Some(interval) => iced::time::every(Duration::from_secs(interval.into()))
+                    .map(|_| Message::LoopNext),
+                None => iced::Subscription::none(),
+            },
         ])
     }
 
@@ -156,19 +393,27 @@ impl App {
                     confy::store(NAME, None, self.set.clone())
                         .is_err()
                         .then(|| println!("ERROR: Failed to save settings"));
-                    Task::batch([
-                        if let Some(settings) = self.window.settings {
-                            window::close(settings)
-                        } else {
-                            Task::none()
-                        },
-                        window::close(self.window.display),
-                        iced::exit(),
-                    ])
+                    if self.recovery.is_none() {
+                        let session = Session {
+                            clean_exit: true,
+                            ..self.service.session()
+                        };
+                        confy::store(NAME, AUTOSAVE, session)
+                            .is_err()
+                            .then(|| println!("ERROR: Failed to save the service"));
+                    }
+                    let secondary = self.window.secondary().map(|window| *window);
+                    Task::batch(
+                        secondary
+                            .into_iter()
+                            .flatten()
+                            .map(window::close)
+                            .chain([window::close(self.window.display), iced::exit()]),
+                    )
                 } else {
-                    if let Some(settings) = self.window.settings {
-                        if id == settings {
-                            self.window.settings = None;
+                    for window in self.window.secondary() {
+                        if *window == Some(id) {
+                            *window = None;
                         }
                     }
                     Task::none()
@@ -178,11 +423,55 @@ impl App {
             Message::SearchChanged(search) => {
                 self.db_select = 0;
                 self.search = search;
-                self.index = load_index(&self.db, self.sort, &self.search)
-                    .expect("ERROR: Failed to load index");
+                self.search_generation += 1;
+                let generation = self.search_generation;
+                Task::perform(tokio::time::sleep(SEARCH_DELAY), move |_| {
+                    Message::Search(generation)
+                })
+            }
+            Message::Search(generation) => {
+                // Only the last search is run
+                if generation != self.search_generation {
+                    return Task::none();
+                }
+                let (db, sort, search, filter) = (
+                    self.search_db.clone(),
+                    self.sort,
+                    self.search.clone(),
+                    self.filter.clone(),
+                );
+                Task::perform(
+                    tokio::task::spawn_blocking(move || {
+                        let db = db.lock().map_err(|e| e.to_string())?;
+                        load_index(&db, sort, &search, &filter).map_err(|e| e.to_string())
+                    }),
+                    move |result| {
+                        Message::IndexLoaded(
+                            generation,
+                            result.unwrap_or_else(|e| Err(e.to_string())),
+                        )
+                    },
+                )
+            }
+            Message::IndexLoaded(generation, index) => {
+                if generation != self.search_generation {
+                    return Task::none();
+                }
+                match index {
+                    Ok(index) => self.index = index,
+                    Err(e) => println!("ERROR: Failed to load index: {}", e),
+                }
+                self.index_scroll.0 = 0.0;
+                scrollable::snap_to("index", scrollable::RelativeOffset::START)
+            }
+            Message::IndexScrolled(viewport) => {
+                self.index_scroll = (viewport.absolute_offset().y, viewport.bounds().height);
                 Task::none()
             }
             Message::ExitSearch => {
+                // The search may still be waiting
+                self.reload_index();
+                self.pane = Pane::Library;
                 if !self.index.is_empty() {
                     self.db_select = self.index[0].0;
                 }
@@ -191,30 +480,291 @@ impl App {
             Message::SortChanged(sort) => {
                 self.db_select = 0;
                 self.sort = sort;
-                self.index = load_index(&self.db, self.sort, &self.search)
-                    .expect("ERROR: Failed to load index");
+                self.reload_index();
+                Task::none()
+            }
+            Message::TopicChanged(topic) => {
+                self.db_select = 0;
+                self.filter.topic = topic;
+                self.reload_index();
+                Task::none()
+            }
+            Message::UsageChanged(usage) => {
+                self.db_select = 0;
+                self.filter.usage = usage;
+                self.reload_index();
                 Task::none()
             }
             Message::SelectSong(id) => {
                 self.db_select = id;
+                self.pane = Pane::Library;
                 Task::none()
             }
             Message::OpenSong(id, content) => {
                 self.service.add(load_song(&self.db, id).ok(), content);
                 Task::none()
             }
+            // Keyboard
+            Message::FocusPane(pane) => {
+                self.pane = pane;
+                Task::none()
+            }
+            Message::Navigate(direction) => match (self.pane, direction) {
+                (Pane::Library, _) => self.move_selection(direction),
+                (Pane::Live, Move::Up) => self.update(Message::Previous(Content::Direct)),
+                (Pane::Live, Move::Down) => self.update(Message::Next(Content::Direct)),
+                (Pane::Live, _) => Task::none(),
+            },
+            Message::PreviewSelected => {
+                self.update(Message::OpenSong(self.db_select, Content::Preview))
+            }
+            Message::GoLiveSelected => {
+                if let Some(index) = self.insert_selected() {
+                    self.service.set_current_song(index);
+                    self.start_item(index);
+                    self.record_usage();
+                }
+                Task::none()
+            }
+            Message::InsertSelected => {
+                self.insert_selected();
+                Task::none()
+            }
+            Message::LibraryAction(laction) => match laction {
+                LAction::Import => {
+                    let extensions: Vec<&str> = Format::ALL
+                        .iter()
+                        .flat_map(|format| format.extensions().iter().copied())
+                        .collect();
+                    Task::perform(
+                        rfd::AsyncFileDialog::new()
+                            .add_filter("Songs", &extensions)
+                            .pick_files(),
+                        |files| {
+                            Message::ImportFiles(
+                                files
+                                    .unwrap_or_default()
+                                    .iter()
+                                    .map(|file| file.path().to_path_buf())
+                                    .collect(),
+                            )
+                        },
+                    )
+                }
+                LAction::Export(format) => {
+                    Task::perform(rfd::AsyncFileDialog::new().pick_folder(), move |folder| {
+                        folder
+                            .map(|folder| Message::ExportSongs(format, folder.path().to_path_buf()))
+                    })
+                    .and_then(Task::done)
+                }
+            },
+            Message::ImportFiles(files) => {
+                match library::Importer::new(&self.db, library::Policy::Skip) {
+                    Ok(mut importer) => {
+                        for file in files {
+                            importer.import_file(&file);
+                        }
+                        println!("Import: {}", importer.report);
+                    }
+                    Err(e) => println!("ERROR: Failed to import: {}", e),
+                }
+                self.reload_entries();
+                Task::none()
+            }
+            Message::OpenPaste => WId::open(&mut self.window.paste),
+            Message::PasteEdited(action) => {
+                self.paste.perform(action);
+                Task::none()
+            }
+            Message::PasteImport => {
+                let record = library::Record::new(plaintext::parse(&self.paste.text()));
+                match library::Importer::new(&self.db, library::Policy::Skip) {
+                    Ok(mut importer) => {
+                        if let Some(id) = importer.import(&record, None) {
+                            self.db_select = id;
+                        }
+                        println!("Import: {}", importer.report);
+                    }
+                    Err(e) => println!("ERROR: Failed to import: {}", e),
+                }
+                self.paste = text_editor::Content::new();
+                self.reload_entries();
+                match self.window.paste.take() {
+                    Some(paste) => window::close(paste),
+                    None => Task::none(),
+                }
+            }
+            Message::OpenManage => {
+                self.reload_entries();
+                WId::open(&mut self.window.manage)
+            }
+            Message::ManageAction(maction) => self.manage(maction),
+            Message::OpenEditor => self.open_editor(),
+            Message::EditorAction(eaction) => self.edit(eaction),
+            Message::OpenPlans => self.open_plans(),
+            Message::PlanAction(paction) => self.plan(paction),
+            Message::FillPlaceholder(index) => {
+                self.service
+                    .fill(index, load_song(&self.db, self.db_select).ok());
+                Task::none()
+            }
+            Message::AddTimer => {
+                self.service.push_timer();
+                Task::none()
+            }
+            Message::TimerAction(index, taction) => {
+                self.timer(index, taction);
+                Task::none()
+            }
+            Message::Tick => {
+                self.tick();
+                Task::none()
+            }
+            Message::PickMedia => self.pick_media(),
+            Message::AddMedia(path) => {
+                match media::Media::from_path(path) {
+                    Some(media) => self.service.push_media(media),
+                    None => println!("ERROR: Not a video or an audio file"),
+                }
+                Task::none()
+            }
+            Message::MediaAction(index, vaction) => self.media(index, vaction),
+            Message::MediaProbed(index, path, probe) => {
+                self.media_probed(index, path, probe);
+                Task::none()
+            }
+            Message::MediaFrame => {
+                self.media_frame();
+                Task::none()
+            }
+            Message::PickBackdrops => self.pick_backdrops(),
+            Message::ImportBackdrops(files) => {
+                backdrop::import(&files);
+                self.backdrops = backdrop::load_library();
+                Task::none()
+            }
+            Message::ToggleBackdrops(index) => {
+                self.backdrop_item = match self.backdrop_item {
+                    Some(open) if open == index => None,
+                    _ => Some(index),
+                };
+                Task::none()
+            }
+            Message::ItemBackdrop(index, background) => {
+                self.service.set_background(index, background);
+                self.backdrop_item = None;
+                Task::none()
+            }
+            Message::ToggleNotes => {
+                self.show_notes = !self.show_notes;
+                Task::none()
+            }
+            Message::ItemNotes(index, notes) => {
+                self.service.set_notes(index, notes);
+                Task::none()
+            }
+            Message::DurationChanged(index, duration) => {
+                self.duration_changed(index, duration);
+                Task::none()
+            }
+            Message::ApplyDuration => {
+                self.apply_duration();
+                Task::none()
+            }
+            Message::EndService => {
+                self.end_service();
+                Task::none()
+            }
+            Message::OpenTimings => self.open_timings(),
+            Message::TimingAction(raction) => {
+                self.timings(raction);
+                Task::none()
+            }
+            Message::Print(layout) => self.print(layout),
+            Message::ExportPrint(layout, path) => {
+                self.export_print(layout, &path);
+                Task::none()
+            }
+            Message::OpenUsage => self.open_usage(),
+            Message::UsageAction(uaction) => self.usage(uaction),
+            Message::ExportUsage(path) => {
+                self.export_usage(&path);
+                Task::none()
+            }
+            Message::ExportSongs(format, folder) => {
+                // Export the songs listed in the library
+                for (id, _) in &self.index {
+                    if let Err(e) = library::export_song(&self.db, *id, format, &folder) {
+                        println!("ERROR: Failed to export song {}: {}", id, e);
+                    }
+                }
+                Task::none()
+            }
             Message::ServiceAction(saction) => self.service.perform(saction),
+            Message::OpenService(path) => {
+                match db::load_service(&path) {
+                    Ok(session) => {
+                        // Another service: the timings and the media of the previous one end
+                        self.end_service();
+                        self.player = None;
+                        self.service.restore(&self.db, &session);
+                    }
+                    Err(e) => println!("ERROR: Failed to open {}: {}", path.display(), e),
+                }
+                Task::none()
+            }
+            Message::SaveService(path) => {
+                if let Err(e) = db::save_service(&path, &self.service.session()) {
+                    println!("ERROR: Failed to save {}: {}", path.display(), e);
+                }
+                Task::none()
+            }
+            Message::Autosave => {
+                // The session of the crash is kept until the user chooses
+                let session = self.service.session();
+                if self.recovery.is_none() && session != self.autosaved {
+                    match confy::store(NAME, AUTOSAVE, &session) {
+                        Ok(()) => self.autosaved = session,
+                        Err(e) => println!("ERROR: Failed to save the service: {}", e),
+                    }
+                }
+                Task::none()
+            }
+            Message::RestoreSession => {
+                if let Some(session) = self.recovery.take() {
+                    self.player = None;
+                    self.service.restore(&self.db, &session);
+                }
+                Task::none()
+            }
+            Message::DismissSession => {
+                self.recovery = None;
+                Task::none()
+            }
+            Message::ServiceNameChanged(name) => {
+                self.service.name = name;
+                Task::none()
+            }
             Message::AddToService => {
                 self.service
                     .push_maybe(load_song(&self.db, self.db_select).ok());
                 Task::none()
             }
             Message::ChangeCurrentSong(index) => {
+                self.pane = Pane::Live;
                 self.service.set_current_song(index);
+                self.start_item(index);
+                self.record_usage();
                 Task::none()
             }
             Message::ChangeScreen(status, content) => {
-                self.service.status[content as usize] = status;
+                let was_song = self.service.status[content as usize] == Status::Song;
+                self.service.set_status(content, status);
+                // Back to the song from black or the logo
+                if content == Content::Direct && !was_song {
+                    self.record_usage();
+                }
                 Task::none()
             }
             Message::ChangeVerse(content, verse) => {
@@ -227,15 +777,28 @@ impl App {
             Message::Next(content) => self.service.change(content, Song::set_next),
             Message::NextChorus(content) => self.service.change(content, Song::set_next_chorus),
             Message::NextVerse(content) => self.service.change(content, Song::set_next_verse),
-            // Settings
-            Message::OpenSettings => {
-                if self.window.settings.is_some() {
-                    return Task::none();
-                }
-                let (settings_id, settings) = window::open(window::Settings::default());
-                self.window.settings = Some(settings_id);
-                settings.map(Message::WindowOpened)
+            Message::LoopIntervalChanged(Interval(interval)) => {
+                self.service.set_interval(interval);
+                Task::none()
+            }
+            Message::LoopToggle => {
+                self.service.looping = !self.service.looping;
+                Task::none()
+            }
+            Message::LoopNext => self
+                .service
+                .change(Content::Direct, Song::set_next_wrapping),
+            // Stage
+            Message::OpenStage => WId::open(&mut self.window.stage),
+            Message::Transpose(semitones) => self
+                .service
+                .change(Content::Direct, |song| song.transpose(semitones)),
+            Message::CapoChanged(capo) => {
+                self.set.capo = capo;
+                Task::none()
             }
+            // Settings
+            Message::OpenSettings => WId::open(&mut self.window.settings),
             Message::SpacingChanged(size) => {
                 self.set.spacing = size;
                 Task::none()
@@ -244,6 +807,46 @@ impl App {
                 self.set.font_size = size;
                 Task::none()
             }
+            Message::FooterChanged(footer) => {
+                self.set.footer = footer;
+                Task::none()
+            }
+            Message::FooterVisibilityChanged(visibility) => {
+                self.set.footer_visibility = visibility;
+                Task::none()
+            }
+            Message::LicenceChanged(licence) => {
+                self.set.ccli_licence = licence;
+                Task::none()
+            }
+            Message::MaxLinesChanged(max_lines) => {
+                self.set.max_lines = max_lines;
+                Task::none()
+            }
+            Message::LogoChanged(logo) => {
+                self.set.logo = logo;
+                Task::none()
+            }
+            Message::PickLogo => Task::perform(
+                rfd::AsyncFileDialog::new()
+                    .add_filter("Images", &backdrop::IMAGE_EXTENSIONS)
+                    .pick_file(),
+                |file| file.map(|file| Message::LogoChanged(file.path().display().to_string())),
+            )
+            .and_then(Task::done),
+            Message::SlideViewChanged(view) => {
+                self.set.slide_view = view;
+                Task::none()
+            }
+            Message::SplitSlides => {
+                // Cut again the songs of the service
+                self.service.max_lines = self.set.max_lines as usize;
+                let session = self.service.session();
+                // The list is rebuilt, the positions of the media no longer hold
+                self.player = None;
+                self.service.restore(&self.db, &session);
+                Task::none()
+            }
             Message::ThemeSelected => {
                 self.set.dark_theme = !self.set.dark_theme;
                 Task::none()
@@ -255,11 +858,69 @@ impl App {
         }
     }
 
+    fn reload_index(&mut self) {
+        // Drop the searches still running
+        self.search_generation += 1;
+        self.index = load_index(&self.db, self.sort, &self.search, &self.filter)
+            .expect("ERROR: Failed to load index");
+    }
+
+    // Insert the selected song after the live item, the positions kept after it move down
+    fn insert_selected(&mut self) -> Option<usize> {
+        let song = match load_song(&self.db, self.db_select) {
+            Ok(song) => song,
+            Err(e) => {
+                println!("ERROR: Failed to load the song: {}", e);
+                return None;
+            }
+        };
+        let index = self.service.insert_next(Some(song))?;
+        let shift = |position: &mut usize| {
+            if *position >= index {
+                *position += 1;
+            }
+        };
+        if let Some(item) = &mut self.backdrop_item {
+            shift(item);
+        }
+        self.timing.shift(index);
+        if let Some(player) = &mut self.player {
+            shift(&mut player.index);
+        }
+        Some(index)
+    }
+
+    // Record the song for the CCLI reports when it is shown live
+    fn record_usage(&self) {
+        if self.service.status[Content::Direct as usize] != Status::Song {
+            return;
+        }
+        if let Some(song) = self.service.current_song(Content::Direct)
+            && let Err(e) = db::record_usage(&self.db, song.id, &self.service.name)
+        {
+            println!("ERROR: Failed to record the usage: {}", e);
+        }
+    }
+
     fn view(&self, id: window::Id) -> Element<'_, Message> {
         let mut screen = if id == self.window.control {
             self.view_control()
         } else if Some(id) == self.window.settings {
             self.view_settings()
+        } else if Some(id) == self.window.stage {
+            self.view_stage()
+        } else if Some(id) == self.window.paste {
+            self.view_paste()
+        } else if Some(id) == self.window.manage {
+            self.view_manage()
+        } else if Some(id) == self.window.editor {
+            self.view_editor()
+        } else if Some(id) == self.window.usage {
+            self.view_usage()
+        } else if Some(id) == self.window.plans {
+            self.view_plans()
+        } else if Some(id) == self.window.timings {
+            self.view_timings()
         } else {
             self.view_display(Content::Direct)
         };
@@ -270,7 +931,7 @@ impl App {
     }
 
     fn theme(&self, id: window::Id) -> Theme {
-        if id == self.window.display || self.set.dark_theme {
+        if id == self.window.display || Some(id) == self.window.stage || self.set.dark_theme {
             Theme::Dark
         } else {
             Theme::Light
@@ -283,4 +944,92 @@ struct WId {
     control: window::Id,
     display: window::Id,
     settings: Option<window::Id>,
+    stage: Option<window::Id>,
+    paste: Option<window::Id>,
+    manage: Option<window::Id>,
+    editor: Option<window::Id>,
+    usage: Option<window::Id>,
+    plans: Option<window::Id>,
+    timings: Option<window::Id>,
+}
+
+impl WId {
+    // Windows opened on demand
+    fn secondary(&mut self) -> [&mut Option<window::Id>; 8] {
+        [
+            &mut self.settings,
+            &mut self.stage,
+            &mut self.paste,
+            &mut self.manage,
+            &mut self.editor,
+            &mut self.usage,
+            &mut self.plans,
+            &mut self.timings,
+        ]
+    }
+
+    fn open(window: &mut Option<window::Id>) -> Task<Message> {
+        if window.is_some() {
+            return Task::none();
+        }
+        let (id, open) = window::open(window::Settings::default());
+        *window = Some(id);
+        open.map(Message::WindowOpened)
+    }
+}
+
+#[cfg(test)]
+impl App {
+    // Without windows, settings, autosave or library on disk
+    fn test(service: Service) -> Self {
+        let db = Connection::open_in_memory().expect("ERROR: Failed to open database");
+        schema::init(&db).expect("ERROR: Failed to create database");
+        let search_db = Connection::open_in_memory().expect("ERROR: Failed to open database");
+        Self {
+            window: WId {
+                control: window::Id::unique(),
+                display: window::Id::unique(),
+                settings: None,
+                stage: None,
+                paste: None,
+                manage: None,
+                editor: None,
+                usage: None,
+                plans: None,
+                timings: None,
+            },
+            resolution: Size::new(1920.0, 1080.0),
+            set: settings::Settings::default(),
+            db: db,
+            db_select: 0,
+            pane: Pane::default(),
+            sort: db::Sort::default(),
+            index: Vec::new(),
+            index_scroll: (0.0, 0.0),
+            search_db: Arc::new(Mutex::new(search_db)),
+            search_generation: 0,
+            service: service,
+            autosaved: Session::default(),
+            recovery: None,
+            books: Vec::new(),
+            search: String::new(),
+            filter: Filter::default(),
+            topics: Vec::new(),
+            paste: text_editor::Content::new(),
+            manage: manage::Manage::default(),
+            editor: editor::Editor::default(),
+            usage: usage::UsageReport::default(),
+            plans: plan::Plans::default(),
+            utc_offset: 0,
+            timer_input: None,
+            player: None,
+            seek: None,
+            media_error: None,
+            backdrops: Vec::new(),
+            backdrop_item: None,
+            show_notes: false,
+            timing: timing::Timing::default(),
+            timings: timing::TimingReport::default(),
+        }
+    }
 }
@@ -0,0 +1,228 @@
+use iced::{
+    Element, Length, Task,
+    widget::{Column, button, column, horizontal_space, pick_list, row, scrollable, text_input},
+};
+
+use crate::{
+    App, Message,
+    db::{self, Entry, Kind},
+    library,
+    song::Book,
+    style,
+    widget::ttext,
+};
+
+// Management window of the authors and songbooks
+#[derive(Debug, Default)]
+pub struct Manage {
+    kind: Kind,
+    entries: Vec<Entry>,
+    selected: Option<u16>,
+    name: String,
+    publisher: String,
+    abbreviation: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MAction {
+    Kind(Kind),
+    Select(u16),
+    NameChanged(String),
+    PublisherChanged(String),
+    AbbreviationChanged(String),
+    Create,
+    Save,
+    Merge(Entry),
+    Delete,
+}
+
+impl Manage {
+    fn select(&mut self, id: Option<u16>, books: &[Book]) {
+        self.selected = id;
+        let entry = self.entries.iter().find(|entry| Some(entry.id) == id);
+        self.name = entry.map(|entry| entry.name.clone()).unwrap_or_default();
+        let book = books.iter().find(|book| Some(book.id) == id);
+        self.publisher = book
+            .and_then(|book| book.publisher.clone())
+            .unwrap_or_default();
+        self.abbreviation = book
+            .and_then(|book| book.abbreviation.clone())
+            .unwrap_or_default();
+    }
+}
+
+fn optional(value: &str) -> Option<String> {
+    let value = value.trim();
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+impl App {
+    pub fn manage(&mut self, maction: MAction) -> Task<Message> {
+        let kind = self.manage.kind;
+        let result = match maction {
+            MAction::Kind(kind) => {
+                self.manage.kind = kind;
+                self.manage.selected = None;
+                Ok(None)
+            }
+            MAction::Select(id) => Ok(Some(id)),
+            MAction::NameChanged(name) => {
+                self.manage.name = name;
+                return Task::none();
+            }
+            MAction::PublisherChanged(publisher) => {
+                self.manage.publisher = publisher;
+                return Task::none();
+            }
+            MAction::AbbreviationChanged(abbreviation) => {
+                self.manage.abbreviation = abbreviation;
+                return Task::none();
+            }
+            MAction::Create => match optional(&self.manage.name) {
+                Some(name) => match kind {
+                    Kind::Authors => library::author_id(&self.db, &name),
+                    Kind::Books => library::book_id(&self.db, &name),
+                    Kind::Topics => library::topic_id(&self.db, &name),
+                }
+                .map(Some),
+                None => return Task::none(),
+            },
+            MAction::Save => match (self.manage.selected, optional(&self.manage.name)) {
+                (Some(id), Some(name)) => {
+                    db::rename_entry(&self.db, kind, id, &name).and_then(|_| {
+                        if kind == Kind::Books {
+                            db::update_book(
+                                &self.db,
+                                &Book {
+                                    id,
+                                    name,
+                                    publisher: optional(&self.manage.publisher),
+                                    abbreviation: optional(&self.manage.abbreviation),
+                                },
+                            )?;
+                        }
+                        Ok(Some(id))
+                    })
+                }
+                _ => return Task::none(),
+            },
+            MAction::Merge(into) => match self.manage.selected {
+                Some(id) => db::merge_entry(&self.db, kind, id, into.id).map(|_| Some(into.id)),
+                None => return Task::none(),
+            },
+            MAction::Delete => match self.manage.selected {
+                Some(id) => db::delete_entry(&self.db, kind, id).map(|_| None),
+                None => return Task::none(),
+            },
+        };
+        match result {
+            Ok(selected) => {
+                self.reload_entries();
+                self.manage.select(selected, &self.books);
+            }
+            Err(e) => println!("ERROR: Failed to edit the {}: {}", kind, e),
+        }
+        Task::none()
+    }
+
+    // Names, songbooks and topics are shown in the library
+    pub fn reload_entries(&mut self) {
+        self.manage.entries =
+            db::load_entries(&self.db, self.manage.kind).expect("ERROR: Failed to load entries");
+        self.books = db::load_songbooks(&self.db).expect("ERROR: Failed to load books");
+        self.topics =
+            db::load_entries(&self.db, Kind::Topics).expect("ERROR: Failed to load topics");
+        // The topic of the filter may have been renamed or deleted
+        self.filter.topic = self
+            .filter
+            .topic
+            .as_ref()
+            .and_then(|topic| self.topics.iter().find(|entry| entry.id == topic.id))
+            .cloned();
+        self.reload_index();
+    }
+
+    pub fn view_manage(&self) -> Element<'_, Message> {
+        let manage = &self.manage;
+        let mut entries = Column::with_capacity(manage.entries.len());
+        for entry in &manage.entries {
+            entries = entries.push(
+                button(ttext(entry.to_string(), self))
+                    .style(if Some(entry.id) == manage.selected {
+                        button::secondary
+                    } else {
+                        button::text
+                    })
+                    .width(Length::Fill)
+                    .on_press(Message::ManageAction(MAction::Select(entry.id))),
+            );
+        }
+        let list = column![
+            pick_list(Kind::ALL, Some(manage.kind), |kind| Message::ManageAction(
+                MAction::Kind(kind)
+            ))
+            .text_size(self.set.font_size)
+            .style(style::theme_pick_list),
+            scrollable(entries).height(Length::Fill),
+        ]
+        .spacing(self.set.spacing)
+        .width(Length::FillPortion(1));
+
+        let selected = manage.selected.is_some();
+        let mut details = column![
+            ttext("Name", self),
+            text_input("Name", &manage.name)
+                .on_input(|name| Message::ManageAction(MAction::NameChanged(name)))
+                .size(self.set.font_size),
+        ]
+        .spacing(self.set.spacing);
+        if manage.kind == Kind::Books {
+            details = details.extend([
+                ttext("Publisher", self).into(),
+                text_input("Publisher", &manage.publisher)
+                    .on_input(|publisher| {
+                        Message::ManageAction(MAction::PublisherChanged(publisher))
+                    })
+                    .size(self.set.font_size)
+                    .into(),
+                ttext("Abbreviation", self).into(),
+                text_input("Abbreviation", &manage.abbreviation)
+                    .on_input(|abbreviation| {
+                        Message::ManageAction(MAction::AbbreviationChanged(abbreviation))
+                    })
+                    .size(self.set.font_size)
+                    .into(),
+            ]);
+        }
+        // The other entries, to merge the selected one into
+        let others: Vec<Entry> = manage
+            .entries
+            .iter()
+            .filter(|entry| Some(entry.id) != manage.selected)
+            .cloned()
+            .collect();
+        details = details.push(
+            row![
+                button(ttext("Create", self)).on_press(Message::ManageAction(MAction::Create)),
+                button(ttext("Save", self))
+                    .on_press_maybe(selected.then(|| Message::ManageAction(MAction::Save))),
+                button(ttext("Delete", self))
+                    .style(button::danger)
+                    .on_press_maybe(selected.then(|| Message::ManageAction(MAction::Delete))),
+                horizontal_space(),
+                pick_list(others, None::<Entry>, |into| Message::ManageAction(
+                    MAction::Merge(into)
+                ))
+                .placeholder("Merge into")
+                .text_size(self.set.font_size)
+                .style(style::theme_pick_list),
+            ]
+            .spacing(self.set.spacing),
+        );
+
+        row![list, details.width(Length::FillPortion(1))]
+            .spacing(self.set.spacing * 5.0)
+            .padding(5)
+            .into()
+    }
+}
@@ -0,0 +1,589 @@
+// Video and audio items of the service, decoded by ffmpeg in the background
+use iced::{
+    Length,
+    advanced::image::Handle,
+    widget::{Column, button, column, row, slider, text, toggler},
+};
+use std::{
+    io::Read,
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use crate::{App, Message, widget::ttext};
+
+pub const VIDEO_EXTENSIONS: [&str; 6] = ["mp4", "mkv", "webm", "mov", "avi", "m4v"];
+pub const AUDIO_EXTENSIONS: [&str; 5] = ["mp3", "ogg", "flac", "wav", "m4a"];
+// Frames are decoded at most this wide, the display scales them
+const FRAME_WIDTH: u32 = 960;
+const FRAME_RATE: u64 = 25;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaKind {
+    Video,
+    Audio,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Media {
+    pub path: PathBuf,
+    pub kind: MediaKind,
+    // The video plays behind the lyrics of the next items
+    pub background: bool,
+    // From 0 to 100
+    pub volume: u8,
+}
+
+// Video and audio actions
+#[derive(Debug, Clone, PartialEq)]
+pub enum VAction {
+    Play,
+    Pause,
+    Stop,
+    // Dragged then applied
+    Seek(f32),
+    ApplySeek,
+    Volume(u8),
+    ApplyVolume,
+    Background(bool),
+}
+
+impl Media {
+    pub fn from_path(path: PathBuf) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+        let kind = if VIDEO_EXTENSIONS.contains(&extension.as_str()) {
+            MediaKind::Video
+        } else if AUDIO_EXTENSIONS.contains(&extension.as_str()) {
+            MediaKind::Audio
+        } else {
+            return None;
+        };
+        Some(Media {
+            path,
+            kind,
+            background: false,
+            volume: 100,
+        })
+    }
+
+    pub fn title(&self) -> String {
+        let name = self
+            .path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        match self.kind {
+            MediaKind::Video => format!("Video: {}", name),
+            MediaKind::Audio => format!("Audio: {}", name),
+        }
+    }
+
+    // Saved in the plans and the autosave as "video 80 background /path/to/file"
+    pub fn spec(&self) -> String {
+        format!(
+            "{} {} {} {}",
+            match self.kind {
+                MediaKind::Video => "video",
+                MediaKind::Audio => "audio",
+            },
+            self.volume,
+            if self.background {
+                "background"
+            } else {
+                "item"
+            },
+            self.path.display()
+        )
+    }
+
+    pub fn from_spec(spec: &str) -> Option<Self> {
+        let mut words = spec.splitn(4, ' ');
+        let kind = match words.next()? {
+            "video" => MediaKind::Video,
+            "audio" => MediaKind::Audio,
+            _ => return None,
+        };
+        let volume = words.next()?.parse().ok()?;
+        let background = words.next()? == "background";
+        Some(Media {
+            path: PathBuf::from(words.next()?),
+            kind,
+            background,
+            volume,
+        })
+    }
+}
+
+// Size of the frames and duration
+pub type Probe = (Option<(u32, u32)>, Option<Duration>);
+
+// Output of ffprobe, like "width=1920\nheight=1080\nduration=12.5"
+fn parse_probe(output: &str) -> Probe {
+    let value = |key: &str| {
+        output
+            .lines()
+            .find_map(|line| line.trim().strip_prefix(key)?.strip_prefix('='))
+    };
+    let size = match (
+        value("width").and_then(|width| width.parse::<u32>().ok()),
+        value("height").and_then(|height| height.parse::<u32>().ok()),
+    ) {
+        (Some(width), Some(height)) if width > 0 => {
+            let scaled = width.min(FRAME_WIDTH);
+            // ffmpeg needs even sizes
+            Some((scaled & !1, (scaled * height / width).max(2) & !1))
+        }
+        _ => None,
+    };
+    let duration = value("duration")
+        .and_then(|duration| duration.parse::<f64>().ok())
+        .map(Duration::from_secs_f64);
+    (size, duration)
+}
+
+// Blocking, run it on a thread
+fn probe(path: &Path) -> Result<Probe, String> {
+    let output = Command::new("ffprobe")
+        .args(["-v", "error", "-select_streams", "v:0"])
+        .args(["-show_entries", "stream=width,height:format=duration"])
+        .args(["-of", "default=noprint_wrappers=1"])
+        .arg(path)
+        .output()
+        .map_err(|e| format!("Failed to run ffprobe, is ffmpeg installed? ({})", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "Failed to read {}: {}",
+            path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(parse_probe(&String::from_utf8_lossy(&output.stdout)))
+}
+
+// Media being played, the position is kept while paused
+#[derive(Debug)]
+pub struct Player {
+    // Item of the service
+    pub index: usize,
+    pub media: Media,
+    pub duration: Option<Duration>,
+    size: Option<(u32, u32)>,
+    offset: Duration,
+    started: Option<Instant>,
+    // The decoder first, then the sound of a video
+    children: Vec<Child>,
+    // Last frame decoded, and the one shown
+    frame: Arc<Mutex<Option<Handle>>>,
+    pub image: Option<Handle>,
+}
+
+impl Player {
+    pub fn new(index: usize, media: Media, (size, duration): Probe) -> Self {
+        Player {
+            index,
+            media,
+            duration,
+            size,
+            offset: Duration::ZERO,
+            started: None,
+            children: Vec::new(),
+            frame: Arc::new(Mutex::new(None)),
+            image: None,
+        }
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.started.is_some()
+    }
+
+    pub fn position(&self) -> Duration {
+        self.offset + self.started.map_or(Duration::ZERO, |since| since.elapsed())
+    }
+
+    // The frames are redrawn at the frame rate, the sound only moves the position
+    pub fn delay(&self) -> Duration {
+        match self.media.kind {
+            MediaKind::Video => Duration::from_millis(1000 / FRAME_RATE),
+            MediaKind::Audio => Duration::from_secs(1),
+        }
+    }
+
+    pub fn play(&mut self) -> Result<(), String> {
+        if self.is_playing() {
+            return Ok(());
+        }
+        let result = match (self.media.kind, self.size) {
+            (MediaKind::Video, Some(size)) => self.spawn_video(size).and_then(|video| {
+                self.children.push(video);
+                // A background is silent
+                if !self.media.background {
+                    let audio = self.spawn_audio(true)?;
+                    self.children.push(audio);
+                }
+                Ok(())
+            }),
+            (MediaKind::Video, None) => {
+                return Err(format!("No video in {}", self.media.path.display()));
+            }
+            (MediaKind::Audio, _) => self
+                .spawn_audio(false)
+                .map(|audio| self.children.push(audio)),
+        };
+        match result {
+            Ok(()) => {
+                self.started = Some(Instant::now());
+                Ok(())
+            }
+            Err(e) => {
+                self.kill();
+                Err(format!(
+                    "Failed to play {}, are ffmpeg and ffplay installed? ({})",
+                    self.media.path.display(),
+                    e
+                ))
+            }
+        }
+    }
+
+    pub fn pause(&mut self) {
+        self.kill();
+        if let Some(since) = self.started.take() {
+            self.offset += since.elapsed();
+        }
+    }
+
+    pub fn seek(&mut self, position: Duration) -> Result<(), String> {
+        let playing = self.is_playing();
+        self.pause();
+        self.offset = position;
+        if playing { self.play() } else { Ok(()) }
+    }
+
+    // Start again where it is, with the new settings
+    pub fn restart(&mut self) -> Result<(), String> {
+        self.seek(self.position())
+    }
+
+    fn kill(&mut self) {
+        for mut child in self.children.drain(..) {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+
+    // Raw RGBA frames are read on a thread, at the speed of the video
+    fn spawn_video(&self, (width, height): (u32, u32)) -> std::io::Result<Child> {
+        let mut child = Command::new("ffmpeg")
+            .args(["-loglevel", "quiet", "-re", "-ss"])
+            .arg(format!("{:.3}", self.offset.as_secs_f64()))
+            .arg("-i")
+            .arg(&self.media.path)
+            .args(["-an", "-f", "rawvideo", "-pix_fmt", "rgba", "-r"])
+            .arg(FRAME_RATE.to_string())
+            .arg("-vf")
+            .arg(format!("scale={}:{}", width, height))
+            .arg("-")
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        if let Some(mut stdout) = child.stdout.take() {
+            let frame = self.frame.clone();
+            std::thread::spawn(move || {
+                let mut pixels = vec![0; (width * height * 4) as usize];
+                while stdout.read_exact(&mut pixels).is_ok() {
+                    if let Ok(mut frame) = frame.lock() {
+                        *frame = Some(Handle::from_rgba(width, height, pixels.clone()));
+                    }
+                }
+            });
+        }
+        Ok(child)
+    }
+
+    fn spawn_audio(&self, video: bool) -> std::io::Result<Child> {
+        let mut command = Command::new("ffplay");
+        command
+            .args(["-loglevel", "quiet", "-nodisp", "-autoexit", "-ss"])
+            .arg(format!("{:.3}", self.offset.as_secs_f64()))
+            .arg("-volume")
+            .arg(self.media.volume.to_string());
+        if video {
+            command.arg("-vn");
+        }
+        command
+            .arg(&self.media.path)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+    }
+
+    // Take the last frame, a background video starts again at the end
+    pub fn update(&mut self) -> Result<(), String> {
+        if let Some(frame) = self.frame.lock().ok().and_then(|mut frame| frame.take()) {
+            self.image = Some(frame);
+        }
+        let ended = match self.children.first_mut() {
+            Some(child) => matches!(child.try_wait(), Ok(Some(_))),
+            None => false,
+        };
+        if ended {
+            self.kill();
+            self.started = None;
+            self.offset = Duration::ZERO;
+            if self.media.kind == MediaKind::Video && self.media.background {
+                return self.play();
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Player {
+    fn drop(&mut self) {
+        self.kill();
+    }
+}
+
+fn minutes_seconds(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+impl App {
+    pub fn pick_media(&self) -> iced::Task<Message> {
+        iced::Task::perform(
+            rfd::AsyncFileDialog::new()
+                .add_filter("Videos", &VIDEO_EXTENSIONS)
+                .add_filter("Audio", &AUDIO_EXTENSIONS)
+                .pick_file(),
+            |file| file.map(|file| Message::AddMedia(file.path().to_path_buf())),
+        )
+        .and_then(iced::Task::done)
+    }
+
+    pub fn media(&mut self, index: usize, vaction: VAction) -> iced::Task<Message> {
+        let player = self.player.as_mut().filter(|player| player.index == index);
+        let result = match (vaction, player) {
+            (VAction::Play, Some(player)) => player.play(),
+            // Probed on a thread, then played
+            (VAction::Play, None) => {
+                if let Some(media) = self.service.media_mut(index) {
+                    let path = media.path.clone();
+                    return iced::Task::perform(
+                        tokio::task::spawn_blocking(move || {
+                            let probe = probe(&path);
+                            (path, probe)
+                        }),
+                        move |result| match result {
+                            Ok((path, probe)) => Message::MediaProbed(index, path, probe),
+                            Err(e) => {
+                                Message::MediaProbed(index, PathBuf::new(), Err(e.to_string()))
+                            }
+                        },
+                    );
+                }
+                Ok(())
+            }
+            (VAction::Pause, Some(player)) => {
+                player.pause();
+                Ok(())
+            }
+            (VAction::Stop, Some(_)) => {
+                self.player = None;
+                Ok(())
+            }
+            (VAction::Seek(position), _) => {
+                self.seek = Some(position);
+                Ok(())
+            }
+            (VAction::ApplySeek, Some(player)) => match self.seek.take() {
+                Some(position) => player.seek(Duration::from_secs_f32(position)),
+                None => Ok(()),
+            },
+            (VAction::ApplySeek, None) => {
+                self.seek = None;
+                Ok(())
+            }
+            (VAction::Volume(volume), player) => {
+                if let Some(media) = self.service.media_mut(index) {
+                    media.volume = volume;
+                }
+                if let Some(player) = player {
+                    player.media.volume = volume;
+                }
+                Ok(())
+            }
+            (VAction::ApplyVolume, Some(player)) if player.is_playing() => player.restart(),
+            (VAction::Background(background), player) => {
+                if let Some(media) = self.service.media_mut(index) {
+                    media.background = background;
+                }
+                match player {
+                    Some(player) => {
+                        player.media.background = background;
+                        player.restart()
+                    }
+                    None => Ok(()),
+                }
+            }
+            _ => Ok(()),
+        };
+        self.media_result(result);
+        iced::Task::none()
+    }
+
+    // One media plays at a time
+    pub fn media_probed(&mut self, index: usize, path: PathBuf, probe: Result<Probe, String>) {
+        let probe = match probe {
+            Ok(probe) => probe,
+            Err(e) => {
+                self.media_result(Err(e));
+                return;
+            }
+        };
+        // The item was removed while probing
+        let Some(media) = self
+            .service
+            .media_mut(index)
+            .filter(|media| media.path == path)
+            .cloned()
+        else {
+            return;
+        };
+        let mut player = Player::new(index, media, probe);
+        let result = player.play();
+        self.player = Some(player);
+        self.media_result(result);
+    }
+
+    // The last error is shown in the control window
+    fn media_result(&mut self, result: Result<(), String>) {
+        match result {
+            Ok(()) => self.media_error = None,
+            Err(e) => {
+                println!("ERROR: {}", e);
+                self.media_error = Some(e);
+            }
+        }
+    }
+
+    // Each frame: a video shown full screen stops when the service moves on
+    pub fn media_frame(&mut self) {
+        let current = self.service.current_song_index();
+        if let Some(player) = &mut self.player {
+            let result = player.update();
+            if player.media.kind == MediaKind::Video
+                && !player.media.background
+                && current != Some(player.index)
+            {
+                self.player = None;
+            }
+            // Only a failure, the frames would clear it
+            if result.is_err() {
+                self.media_result(result);
+            }
+        }
+    }
+
+    // Last frame of the video on the display
+    pub fn frame(&self) -> Option<Handle> {
+        let player = self.player.as_ref()?;
+        let current = self.service.current_song_index()?;
+        match player.media.kind {
+            MediaKind::Video if player.media.background || player.index == current => {
+                player.image.clone()
+            }
+            _ => None,
+        }
+    }
+
+    pub fn view_media(&self, index: usize, media: &Media) -> Column<'_, Message> {
+        let action = move |vaction| Message::MediaAction(index, vaction);
+        let player = self.player.as_ref().filter(|player| player.index == index);
+        let playing = player.is_some_and(Player::is_playing);
+        let position = player.map_or(Duration::ZERO, Player::position);
+        let duration = player.and_then(|player| player.duration);
+        let mut controls = column![
+            ttext(
+                match duration {
+                    Some(duration) => format!(
+                        "{} / {}",
+                        minutes_seconds(position),
+                        minutes_seconds(duration)
+                    ),
+                    None => minutes_seconds(position),
+                },
+                self
+            )
+            .size(self.set.font_size * 2.0)
+            .width(Length::Fill)
+            .center(),
+            row![
+                button(ttext("Play", self))
+                    .on_press_maybe((!playing).then(|| action(VAction::Play))),
+                button(ttext("Pause", self))
+                    .on_press_maybe(playing.then(|| action(VAction::Pause))),
+                button(ttext("Stop", self))
+                    .style(button::secondary)
+                    .on_press_maybe(player.is_some().then(|| action(VAction::Stop))),
+            ]
+            .spacing(self.set.spacing),
+        ]
+        .spacing(self.set.spacing * 2.0);
+        if let Some(error) = &self.media_error {
+            controls = controls.push(ttext(error.as_str(), self).style(text::danger));
+        }
+        if let Some(duration) = duration {
+            controls = controls.push(
+                slider(
+                    0.0..=duration.as_secs_f32(),
+                    self.seek.unwrap_or(position.as_secs_f32()),
+                    move |position| action(VAction::Seek(position)),
+                )
+                .on_release(action(VAction::ApplySeek)),
+            );
+        }
+        controls = controls.push(
+            row![
+                ttext("Volume", self),
+                slider(0..=100, media.volume, move |volume| action(
+                    VAction::Volume(volume)
+                ))
+                .on_release(action(VAction::ApplyVolume)),
+            ]
+            .spacing(self.set.spacing),
+        );
+        if media.kind == MediaKind::Video {
+            controls = controls.push(
+                toggler(media.background)
+                    .label("Play behind the lyrics")
+                    .on_toggle(move |on| action(VAction::Background(on)))
+                    .size(self.set.font_size)
+                    .text_size(self.set.font_size),
+            );
+        }
+        controls
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn media_spec() {
+        let media = Media::from_path(PathBuf::from("/videos/My Loop.MP4")).unwrap();
+        assert_eq!(media.kind, MediaKind::Video);
+        assert_eq!(media.spec(), "video 100 item /videos/My Loop.MP4");
+        let media = Media::from_spec("audio 60 background /music/a b.ogg").unwrap();
+        assert_eq!(media.path, PathBuf::from("/music/a b.ogg"));
+        assert!(media.background && media.volume == 60);
+        let (size, duration) = parse_probe("width=1920\nheight=1080\nduration=12.500000\n");
+        assert_eq!(size, Some((960, 540)));
+        assert_eq!(duration, Some(Duration::from_millis(12500)));
+    }
+}
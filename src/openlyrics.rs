@@ -0,0 +1,322 @@
+// OpenLyrics XML files: https://docs.openlyrics.org/en/latest/dataformat.html
+use quick_xml::{
+    Reader,
+    escape::{escape, resolve_predefined_entity},
+    events::{BytesStart, Event},
+};
+
+use crate::{
+    library::Record,
+    song::{Song, Verse},
+};
+
+pub fn parse(input: &str) -> Result<Record, quick_xml::Error> {
+    let mut record = Record::default();
+    let mut titles: Vec<String> = vec![];
+    let mut comments: Vec<String> = vec![];
+    let mut section: Option<Verse> = None;
+    let mut text = String::new();
+    // Text of the current property
+    let mut value = String::new();
+    let mut lines = false;
+    let mut reader = Reader::from_str(input);
+    loop {
+        match reader.read_event()? {
+            Event::Eof => break,
+            Event::Start(start) => {
+                let name = local_name(&start);
+                match name.as_str() {
+                    "verse" => {
+                        section = attribute(&start, "name")?.and_then(|name| verse(&name));
+                        text.clear();
+                    }
+                    "lines" => {
+                        if !text.is_empty() {
+                            text += "\n";
+                        }
+                        lines = true;
+                    }
+                    "chord" if lines => chord(&start, &mut text)?,
+                    "songbook" => songbook(&start, &mut record)?,
+                    _ => (),
+                }
+                value.clear();
+            }
+            Event::Empty(empty) => match local_name(&empty).as_str() {
+                "br" if lines => text += "\n",
+                "chord" if lines => chord(&empty, &mut text)?,
+                "songbook" => songbook(&empty, &mut record)?,
+                _ => (),
+            },
+            Event::Text(content) => {
+                let content = content.decode().map_err(quick_xml::Error::from)?;
+                if lines {
+                    // Line breaks are only given by <br/>
+                    text += &content.replace(['\n', '\r'], "");
+                } else {
+                    value += &content;
+                }
+            }
+            Event::CData(content) => text += &content.decode().map_err(quick_xml::Error::from)?,
+            Event::GeneralRef(reference) => {
+                let content = match reference.resolve_char_ref()? {
+                    Some(c) => c.to_string(),
+                    None => {
+                        let name = reference.decode().map_err(quick_xml::Error::from)?;
+                        resolve_predefined_entity(&name)
+                            .unwrap_or_default()
+                            .to_string()
+                    }
+                };
+                if lines {
+                    text += &content;
+                } else {
+                    value += &content;
+                }
+            }
+            Event::End(end) => match end.local_name().as_ref() {
+                b"lines" => lines = false,
+                b"verse" => {
+                    let lyrics = text
+                        .lines()
+                        .map(str::trim)
+                        .collect::<Vec<&str>>()
+                        .join("\n");
+                    text.clear();
+                    push_verse(&mut record.song, section.take(), lyrics);
+                }
+                name => {
+                    let name = String::from_utf8_lossy(name);
+                    property(&name, value.trim(), &mut record, &mut titles, &mut comments);
+                    value.clear();
+                }
+            },
+            _ => (),
+        }
+    }
+    let mut titles = titles.into_iter();
+    record.song.title = titles.next().unwrap_or_default();
+    record.song.alternate_title = titles.next();
+    if !comments.is_empty() {
+        record.song.comments = Some(comments.join("\n"));
+    }
+    Ok(record)
+}
+
+fn local_name(element: &BytesStart) -> String {
+    String::from_utf8_lossy(element.local_name().as_ref()).to_string()
+}
+
+fn attribute(element: &BytesStart, name: &str) -> Result<Option<String>, quick_xml::Error> {
+    for attribute in element.attributes() {
+        let attribute = attribute.map_err(quick_xml::Error::from)?;
+        if attribute.key.local_name().as_ref() == name.as_bytes() {
+            return Ok(Some(attribute.unescape_value()?.to_string()));
+        }
+    }
+    Ok(None)
+}
+
+fn chord(element: &BytesStart, text: &mut String) -> Result<(), quick_xml::Error> {
+    if let Some(name) = attribute(element, "name")? {
+        *text += &format!("[{}]", name);
+    }
+    Ok(())
+}
+
+fn songbook(element: &BytesStart, record: &mut Record) -> Result<(), quick_xml::Error> {
+    // Only the first songbook is kept
+    if record.book.is_none() {
+        record.book = attribute(element, "name")?;
+        record.song.number = attribute(element, "entry")?.and_then(|entry| {
+            let digits: String = entry.chars().take_while(char::is_ascii_digit).collect();
+            digits.parse().ok()
+        });
+    }
+    Ok(())
+}
+
+fn property(
+    element: &str,
+    content: &str,
+    record: &mut Record,
+    titles: &mut Vec<String>,
+    comments: &mut Vec<String>,
+) {
+    if content.is_empty() {
+        return;
+    }
+    let content = content.to_string();
+    let song = &mut record.song;
+    match element {
+        "title" => titles.push(content),
+        "author" => record.authors.push(content),
+        "theme" => record.topics.push(content),
+        "comment" => comments.push(content),
+        "copyright" => song.copyright = Some(content),
+        "ccliNo" => song.ccli_number = Some(content),
+        "verseOrder" => song.verse_order = Some(content),
+        _ => (),
+    }
+}
+
+// "v1", "c", "v1a": the parts of a verse are joined in one verse
+fn verse(name: &str) -> Option<Verse> {
+    let name = match name.find(|c: char| c.is_ascii_digit()) {
+        Some(_) => name.trim_end_matches(|c: char| c.is_ascii_alphabetic()),
+        None => name,
+    };
+    Verse::from_label(name)
+}
+
+fn push_verse(song: &mut Song, verse: Option<Verse>, mut lyrics: String) {
+    if let Some((last, text)) = song.lyrics.last_mut()
+        && verse == Some(*last)
+    {
+        *text += "\n";
+        *text += &lyrics;
+        return;
+    }
+    song.push_verse(verse, &mut lyrics);
+}
+
+pub fn export(record: &Record) -> String {
+    let song = &record.song;
+    let mut file = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<song xmlns=\"http://openlyrics.info/namespace/2009/song\" version=\"0.9\" createdIn=\"RustLP\">
+  <properties>
+    <titles>
+",
+    );
+    file += &format!("      <title>{}</title>\n", escape(&song.title));
+    if let Some(title) = &song.alternate_title {
+        file += &format!("      <title>{}</title>\n", escape(title));
+    }
+    file += "    </titles>\n";
+    if !record.authors.is_empty() {
+        file += "    <authors>\n";
+        for author in &record.authors {
+            file += &format!("      <author>{}</author>\n", escape(author));
+        }
+        file += "    </authors>\n";
+    }
+    if let Some(copyright) = &song.copyright {
+        file += &format!("    <copyright>{}</copyright>\n", escape(copyright));
+    }
+    if let Some(ccli) = &song.ccli_number {
+        file += &format!("    <ccliNo>{}</ccliNo>\n", escape(ccli));
+    }
+    if let Some(order) = &song.verse_order {
+        // The names of the verses are in lowercase
+        file += &format!(
+            "    <verseOrder>{}</verseOrder>\n",
+            escape(order.to_lowercase())
+        );
+    }
+    if let Some(book) = &record.book {
+        let entry = song
+            .number
+            .map(|number| format!(" entry=\"{}\"", number))
+            .unwrap_or_default();
+        file += &format!(
+            "    <songbooks>\n      <songbook name=\"{}\"{}/>\n    </songbooks>\n",
+            escape(book),
+            entry
+        );
+    }
+    if !record.topics.is_empty() {
+        file += "    <themes>\n";
+        for topic in &record.topics {
+            file += &format!("      <theme>{}</theme>\n", escape(topic));
+        }
+        file += "    </themes>\n";
+    }
+    if let Some(comments) = &song.comments {
+        file += "    <comments>\n";
+        for comment in comments.lines() {
+            file += &format!("      <comment>{}</comment>\n", escape(comment));
+        }
+        file += "    </comments>\n";
+    }
+    file += "  </properties>\n  <lyrics>\n";
+    for (verse, text) in &song.lyrics {
+        let lines: Vec<String> = text.lines().map(lyrics_line).collect();
+        file += &format!(
+            "    <verse name=\"{}{}\">\n      <lines>{}</lines>\n    </verse>\n",
+            verse.kind().tag(),
+            verse.number(),
+            lines.join("<br/>")
+        );
+    }
+    file += "  </lyrics>\n</song>\n";
+    file
+}
+
+// "[G]Amazing grace" -> "<chord name="G"/>Amazing grace"
+fn lyrics_line(line: &str) -> String {
+    let mut xml = String::new();
+    let mut rest = line;
+    while let Some(start) = rest.find('[') {
+        let Some(end) = rest[start..].find(']') else {
+            break;
+        };
+        xml += &escape(&rest[..start]);
+        xml += &format!(
+            "<chord name=\"{}\"/>",
+            escape(&rest[start + 1..start + end])
+        );
+        rest = &rest[start + end + 1..];
+    }
+    xml += &escape(rest);
+    xml
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn openlyrics() {
+        let input = r#"<?xml version="1.0" encoding="UTF-8"?>
+<song xmlns="http://openlyrics.info/namespace/2009/song" version="0.8">
+  <properties>
+    <titles><title>Amazing Grace</title><title>New Britain</title></titles>
+    <authors><author>John Newton</author></authors>
+    <copyright>Public Domain &amp; friends</copyright>
+    <verseOrder>v1 c v2</verseOrder>
+    <songbooks><songbook name="JEM" entry="48a"/></songbooks>
+    <themes><theme>Grace</theme></themes>
+  </properties>
+  <lyrics>
+    <verse name="v1">
+      <lines><chord name="G"/>Amazing grace how sweet<br/>
+      That saved a wretch like me</lines>
+    </verse>
+    <verse name="c"><lines>My chains are gone</lines></verse>
+    <verse name="v2a"><lines>I once was lost</lines></verse>
+    <verse name="v2b"><lines>But now am found</lines></verse>
+  </lyrics>
+</song>"#;
+        let record = parse(input).unwrap();
+        let song = &record.song;
+        assert_eq!(song.title, "Amazing Grace");
+        assert_eq!(song.alternate_title.as_deref(), Some("New Britain"));
+        assert_eq!(song.copyright.as_deref(), Some("Public Domain & friends"));
+        assert_eq!(song.verse_order.as_deref(), Some("v1 c v2"));
+        assert_eq!(song.number, Some(48));
+        assert_eq!(record.book.as_deref(), Some("JEM"));
+        assert_eq!(record.authors, ["John Newton"]);
+        assert_eq!(record.topics, ["Grace"]);
+        let verses: Vec<String> = song.lyrics.iter().map(|l| l.0.to_string()).collect();
+        assert_eq!(verses, ["V1", "C1", "V2"]);
+        assert_eq!(
+            song.lyrics[0].1,
+            "[G]Amazing grace how sweet\nThat saved a wretch like me"
+        );
+        assert_eq!(song.lyrics[2].1, "I once was lost\nBut now am found");
+        // Export then import again
+        let again = parse(&export(&record)).unwrap();
+        assert_eq!(again, record);
+    }
+}
@@ -0,0 +1,27 @@
+use iced::{
+    Element, Length,
+    widget::{button, column, horizontal_space, row, text_editor},
+};
+
+use crate::{App, Message, widget::ttext};
+
+impl App {
+    // Import a song copied from a text file or from SongSelect
+    pub fn view_paste(&self) -> Element<'_, Message> {
+        column![
+            ttext("Title, then sections like \"Verse 1\" or \"Chorus\"", self),
+            text_editor(&self.paste)
+                .placeholder("Paste the song here")
+                .on_action(Message::PasteEdited)
+                .size(self.set.font_size)
+                .height(Length::Fill),
+            row![
+                horizontal_space(),
+                button(ttext("Import", self)).on_press(Message::PasteImport)
+            ],
+        ]
+        .spacing(self.set.spacing)
+        .padding(5)
+        .into()
+    }
+}
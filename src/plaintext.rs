@@ -0,0 +1,230 @@
+// Plain text songs and CCLI SongSelect text exports:
+//
+// Title
+//
+// Verse 1
+// ...
+//
+// Chorus
+// ...
+//
+// CCLI Song # 1234567
+// Author One | Author Two
+// © 2001 Publisher
+// For use solely with the SongSelect® Terms of Use. All rights reserved. www.ccli.com
+// CCLI License # 123456
+use crate::{
+    chords,
+    song::{Song, Verse},
+};
+
+// Parse a text song, return the song and its authors
+pub fn parse(input: &str) -> (Song, Vec<String>) {
+    let mut song = Song::default();
+    let mut authors = vec![];
+    let mut section: Option<Verse> = None;
+    let mut text = String::new();
+    let mut footer = false;
+    let mut lines = input
+        .lines()
+        .map(str::trim)
+        .skip_while(|line| line.is_empty());
+    // The first line is the title, even a "Refrain"
+    if let Some(title) = lines.next() {
+        song.title = title.to_string();
+    }
+    for line in lines {
+        let lowercase = line.to_lowercase();
+        if let Some(ccli) = ccli_number(&lowercase) {
+            footer = true;
+            song.ccli_number = Some(ccli);
+        } else if line.starts_with('©')
+            || lowercase.starts_with("(c)")
+            || lowercase.starts_with("copyright")
+        {
+            footer = true;
+            let mut copyright = line;
+            for prefix in ["Copyright", "copyright", "©", "(c)", "(C)"] {
+                copyright = copyright.trim_start().trim_start_matches(prefix);
+            }
+            song.copyright = Some(copyright.trim().to_string());
+        } else if lowercase.contains("ccli") || lowercase.contains("songselect") {
+            // Terms of use and licence of the church
+            footer = true;
+        } else if footer {
+            if authors.is_empty() && !line.is_empty() {
+                authors = line
+                    .split(['|', ','])
+                    .map(str::trim)
+                    .filter(|author| !author.is_empty())
+                    .map(String::from)
+                    .collect();
+            }
+        } else if let Some(verse) = heading(line) {
+            song.push_verse(section.take(), &mut text);
+            section = Some(verse);
+        } else if line.is_empty() {
+            song.push_verse(section.take(), &mut text);
+        } else {
+            text += line;
+            text += "\n";
+        }
+    }
+    song.push_verse(section, &mut text);
+    (song, authors)
+}
+
+pub fn export(song: &Song, authors: &[String]) -> String {
+    let mut file = format!("{}\n", song.title);
+    for (verse, text) in &song.lyrics {
+        file += &format!(
+            "\n{} {}\n{}\n",
+            verse.kind().name(),
+            verse.number(),
+            chords::strip(text)
+        );
+    }
+    file += "\n";
+    if let Some(ccli) = &song.ccli_number {
+        file += &format!("CCLI Song # {}\n", ccli);
+    }
+    if !authors.is_empty() {
+        file += &format!("{}\n", authors.join(" | "));
+    }
+    if let Some(copyright) = &song.copyright {
+        file += &format!("© {}\n", copyright);
+    }
+    file
+}
+
+// Names read without brackets nor number, the shorter ones are also words of the lyrics
+const HEADINGS: [&str; 28] = [
+    "intro",
+    "introduction",
+    "introducción",
+    "introdução",
+    "verse",
+    "couplet",
+    "strophe",
+    "estrofa",
+    "strofa",
+    "pre-chorus",
+    "prechorus",
+    "pre chorus",
+    "pré-refrain",
+    "pre-refrain",
+    "vorrefrain",
+    "pre-coro",
+    "pré-refrão",
+    "chorus",
+    "refrain",
+    "refrein",
+    "estribillo",
+    "refrão",
+    "ritornello",
+    "bridge",
+    "ending",
+    "outro",
+    "interlude",
+    "instrumental",
+];
+
+fn heading(line: &str) -> Option<Verse> {
+    match line
+        .strip_prefix('[')
+        .and_then(|line| line.strip_suffix(']'))
+    {
+        // "[V1]" or "[Chorus]", but a single chord like "[C]" is not a heading
+        Some(label) => Verse::from_label(label).filter(|_| !is_chord(label)),
+        // "Verse 1", "V1" or "Chorus", but a lyric like "Fin" or "O" is not a heading
+        None => {
+            let label = line.trim_end_matches(':');
+            let name = label.trim_end_matches(|c: char| c.is_ascii_digit() || c == ' ');
+            let numbered = name.len() < label.len();
+            if numbered || HEADINGS.contains(&name.to_lowercase().as_str()) {
+                Verse::from_label(line)
+            } else {
+                None
+            }
+        }
+    }
+}
+
+// A bare chord, like "C" or "Bb"
+fn is_chord(label: &str) -> bool {
+    let mut chars = label.trim().chars();
+    matches!(chars.next(), Some('A'..='G')) && chars.all(|c| c == '#' || c == 'b')
+}
+
+// "CCLI Song # 1234567", "CCLI-Liednummer 1234567", "CCLI Song No. 1234567"
+fn ccli_number(line: &str) -> Option<String> {
+    if !line.starts_with("ccli") || line.contains("licen") || line.contains("lizenz") {
+        return None;
+    }
+    let number: String = line
+        .chars()
+        .skip_while(|c| !c.is_ascii_digit())
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+    (!number.is_empty()).then_some(number)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn songselect() {
+        let input = "Amazing Grace
+
+Verse 1
+Amazing grace how sweet the sound
+That saved a wretch like me
+
+Refrain
+My chains are gone
+
+Couplet 2
+I once was lost
+
+CCLI Song # 4768151
+John Newton | Chris Tomlin
+© 2006 worshiptogether.com songs
+For use solely with the SongSelect® Terms of Use. All rights reserved. www.ccli.com
+CCLI License # 123456
+";
+        let (song, authors) = parse(input);
+        assert_eq!(song.title, "Amazing Grace");
+        assert_eq!(song.ccli_number.as_deref(), Some("4768151"));
+        assert_eq!(
+            song.copyright.as_deref(),
+            Some("2006 worshiptogether.com songs")
+        );
+        assert_eq!(authors, ["John Newton", "Chris Tomlin"]);
+        let verses: Vec<String> = song.lyrics.iter().map(|l| l.0.to_string()).collect();
+        assert_eq!(verses, ["V1", "C1", "V2"]);
+        assert_eq!(
+            song.lyrics[0].1,
+            "Amazing grace how sweet the sound\nThat saved a wretch like me"
+        );
+        // Export then import again
+        let (again, authors) = parse(&export(&song, &authors));
+        assert_eq!(again, song);
+        assert_eq!(authors, ["John Newton", "Chris Tomlin"]);
+        // Short headings in brackets, not the chords
+        let label = |line| heading(line).map(|verse| verse.to_string());
+        assert_eq!(label("[V1]").as_deref(), Some("V1"));
+        assert_eq!(label("[C2]").as_deref(), Some("C2"));
+        assert_eq!(label("[Chorus]").as_deref(), Some("C1"));
+        assert_eq!(label("[C]"), None);
+        assert_eq!(label("Coro 2").as_deref(), Some("C2"));
+        for lyric in ["Tag", "Final", "Fin", "Coro", "Vers", "O", "E"] {
+            assert_eq!(label(lyric), None);
+        }
+        // A title like a heading
+        let (song, _) = parse("Refrain\n\nVerse 1\nHallelujah\n\nTag\nFin\n");
+        assert_eq!(song.title, "Refrain");
+        assert_eq!(song.lyrics.len(), 2);
+        assert_eq!(song.lyrics[1].1, "Tag\nFin");
+    }
+}
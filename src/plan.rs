@@ -0,0 +1,304 @@
+// Dated service plans, and the templates they start from
+use iced::{
+    Element, Length, Task,
+    widget::{Column, button, column, horizontal_space, row, scrollable, text_input},
+};
+use rusqlite::{Connection, Result, params};
+use std::fmt::Display;
+
+use crate::{
+    App, Message,
+    db::{Item, Service},
+    library::load_song,
+    media::Media,
+    style,
+    timer::Timer,
+    widget::ttext,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Plan {
+    pub id: u16,
+    pub name: String,
+    // None for a template
+    pub date: Option<String>,
+}
+
+impl Display for Plan {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.date {
+            Some(date) => write!(f, "{} {}", date, self.name),
+            None => write!(f, "{} (template)", self.name),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Plans {
+    plans: Vec<Plan>,
+    // Plans from today, listed in the control window
+    pub upcoming: Vec<Plan>,
+    date: String,
+    slot: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PAction {
+    Open(Plan),
+    Delete(u16),
+    DateChanged(String),
+    SlotChanged(String),
+    AddSlot,
+    Save,
+}
+
+// The templates first, then the latest plans
+pub fn load_plans(db: &Connection) -> Result<Vec<Plan>> {
+    let mut query = db.prepare(
+        "SELECT id, name, date FROM plans
+            ORDER BY date IS NOT NULL, date DESC, name;",
+    )?;
+    query
+        .query_map([], |row| {
+            Ok(Plan {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                date: row.get(2)?,
+            })
+        })?
+        .collect()
+}
+
+pub fn load_upcoming(db: &Connection) -> Result<Vec<Plan>> {
+    let mut query = db.prepare(
+        "SELECT id, name, date FROM plans
+            WHERE date >= date('now', 'localtime')
+            ORDER BY date, name;",
+    )?;
+    query
+        .query_map([], |row| {
+            Ok(Plan {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                date: row.get(2)?,
+            })
+        })?
+        .collect()
+}
+
+// A deleted song leaves its slot empty
+fn load_items(db: &Connection, plan: u16) -> Result<Vec<Item>> {
+    let mut query = db.prepare(
+        "SELECT slot, song_id, timer, interval, media, background, notes, duration FROM plan_items
+            WHERE plan_id = ?1
+            ORDER BY position;",
+    )?;
+    let rows = query
+        .query_map([plan], |row| {
+            Ok((
+                row.get::<_, Option<String>>(0)?,
+                row.get::<_, Option<u16>>(1)?,
+                row.get::<_, Option<String>>(2)?,
+                row.get::<_, u32>(3)?,
+                row.get::<_, Option<String>>(4)?,
+                row.get::<_, Option<String>>(5)?,
+                row.get::<_, String>(6)?,
+                row.get::<_, u32>(7)?,
+            ))
+        })?
+        .collect::<Result<Vec<_>>>()?;
+    Ok(rows
+        .into_iter()
+        .map(
+            |(slot, song, timer, interval, media, background, notes, duration)| {
+                let item = if let Some(timer) = timer.as_deref().and_then(Timer::from_spec) {
+                    Item::timer(timer)
+                } else if let Some(media) = media.as_deref().and_then(Media::from_spec) {
+                    Item::media(media)
+                } else {
+                    match song.and_then(|id| load_song(db, id).ok()) {
+                        Some(song) => Item::song(song),
+                        None => Item::placeholder(slot.clone().unwrap_or_default()),
+                    }
+                };
+                Item {
+                    slot,
+                    interval,
+                    background,
+                    notes,
+                    duration,
+                    ..item
+                }
+            },
+        )
+        .collect())
+}
+
+// A plan with the same name and date is replaced
+fn save_plan(db: &Connection, service: &Service, date: Option<&str>) -> Result<()> {
+    let transaction = db.unchecked_transaction()?;
+    transaction.execute(
+        "DELETE FROM plan_items WHERE plan_id IN
+            (SELECT id FROM plans WHERE name = ?1 AND date IS ?2);",
+        params![service.name, date],
+    )?;
+    transaction.execute(
+        "DELETE FROM plans WHERE name = ?1 AND date IS ?2;",
+        params![service.name, date],
+    )?;
+    transaction.execute(
+        "INSERT INTO plans (name, date) VALUES (?1, ?2);",
+        params![service.name, date],
+    )?;
+    let plan = transaction.last_insert_rowid();
+    for (position, item) in service.iter().enumerate() {
+        transaction.execute(
+            "INSERT INTO plan_items (plan_id, position, slot, song_id, timer, interval, media,
+                    background, notes, duration)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10);",
+            params![
+                plan,
+                position,
+                item.slot,
+                item.as_song().map(|song| song.id),
+                item.as_timer().map(Timer::spec),
+                item.interval,
+                item.as_media().map(Media::spec),
+                item.background,
+                item.notes,
+                item.duration
+            ],
+        )?;
+    }
+    transaction.commit()
+}
+
+fn delete_plan(db: &Connection, plan: u16) -> Result<()> {
+    db.execute("DELETE FROM plan_items WHERE plan_id = ?1;", [plan])?;
+    db.execute("DELETE FROM plans WHERE id = ?1;", [plan])?;
+    Ok(())
+}
+
+impl App {
+    pub fn open_plans(&mut self) -> Task<Message> {
+        if self.plans.date.is_empty() {
+            // Next Sunday by default
+            if let Ok(date) =
+                self.db
+                    .query_row("SELECT date('now', 'localtime', 'weekday 0');", [], |row| {
+                        row.get(0)
+                    })
+            {
+                self.plans.date = date;
+            }
+        }
+        self.reload_plans();
+        crate::WId::open(&mut self.window.plans)
+    }
+
+    pub fn reload_plans(&mut self) {
+        self.plans.plans = load_plans(&self.db).expect("ERROR: Failed to load the plans");
+        self.plans.upcoming = load_upcoming(&self.db).expect("ERROR: Failed to load the plans");
+    }
+
+    pub fn plan(&mut self, paction: PAction) -> Task<Message> {
+        match paction {
+            PAction::Open(plan) => match load_items(&self.db, plan.id) {
+                Ok(items) => {
+                    // A template keeps the date chosen, to save it as a plan
+                    if let Some(date) = plan.date {
+                        self.plans.date = date;
+                    }
+                    // Another service: the timings and the media of the previous one end
+                    self.end_service();
+                    self.player = None;
+                    self.service.replace(plan.name, items);
+                }
+                Err(e) => println!("ERROR: Failed to open the plan {}: {}", plan, e),
+            },
+            PAction::Delete(id) => {
+                if let Err(e) = delete_plan(&self.db, id) {
+                    println!("ERROR: Failed to delete the plan: {}", e);
+                }
+                self.reload_plans();
+            }
+            PAction::DateChanged(date) => self.plans.date = date,
+            PAction::SlotChanged(slot) => self.plans.slot = slot,
+            PAction::AddSlot => {
+                let slot = self.plans.slot.trim();
+                if !slot.is_empty() {
+                    self.service.push_placeholder(slot.to_string());
+                }
+                self.plans.slot.clear();
+            }
+            PAction::Save => {
+                if self.service.name.trim().is_empty() {
+                    println!("ERROR: The service needs a name to be saved");
+                    return Task::none();
+                }
+                let date = self.plans.date.trim();
+                let date = (!date.is_empty()).then_some(date);
+                if let Err(e) = save_plan(&self.db, &self.service, date) {
+                    println!("ERROR: Failed to save the plan: {}", e);
+                }
+                self.reload_plans();
+            }
+        }
+        Task::none()
+    }
+
+    pub fn view_plans(&self) -> Element<'_, Message> {
+        let plans = &self.plans;
+        let mut list = Column::with_capacity(plans.plans.len());
+        for plan in &plans.plans {
+            list = list.push(
+                row![
+                    ttext(plan.to_string(), self).width(Length::Fill),
+                    button(ttext("Open", self))
+                        .on_press(Message::PlanAction(PAction::Open(plan.clone()))),
+                    button(ttext("Delete", self))
+                        .style(button::danger)
+                        .on_press(Message::PlanAction(PAction::Delete(plan.id))),
+                ]
+                .spacing(self.set.spacing),
+            );
+        }
+        let save = column![
+            ttext("Service name", self),
+            text_input("Service name", &self.service.name)
+                .on_input(Message::ServiceNameChanged)
+                .size(self.set.font_size),
+            ttext("Date, empty for a template", self),
+            text_input("YYYY-MM-DD", &plans.date)
+                .on_input(|date| Message::PlanAction(PAction::DateChanged(date)))
+                .size(self.set.font_size),
+            ttext("Placeholder", self),
+            row![
+                text_input("Scripture, Sermon…", &plans.slot)
+                    .on_input(|slot| Message::PlanAction(PAction::SlotChanged(slot)))
+                    .on_submit(Message::PlanAction(PAction::AddSlot))
+                    .size(self.set.font_size),
+                button(ttext("Add", self)).on_press(Message::PlanAction(PAction::AddSlot)),
+            ]
+            .spacing(self.set.spacing),
+            row![
+                ttext(format!("{} items", self.service.iter().len()), self).style(style::soft_text),
+                horizontal_space(),
+                button(ttext("Save the service", self))
+                    .on_press(Message::PlanAction(PAction::Save)),
+            ],
+        ]
+        .spacing(self.set.spacing)
+        .width(Length::FillPortion(1));
+
+        row![
+            scrollable(list)
+                .height(Length::Fill)
+                .width(Length::FillPortion(1)),
+            save
+        ]
+        .spacing(self.set.spacing * 5.0)
+        .padding(5)
+        .into()
+    }
+}
@@ -0,0 +1,438 @@
+// Printable exports of the service: running order for the tech team, lyric sheets for the musicians
+use iced::Task;
+use std::{fmt::Display, path::Path};
+
+use crate::{
+    App, Message, chords,
+    db::{ItemKind, Service},
+    song::Book,
+};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Layout {
+    #[default]
+    RunningOrder,
+    LyricSheets,
+    ChordSheets,
+}
+
+impl Layout {
+    pub const ALL: [Layout; 3] = [
+        Layout::RunningOrder,
+        Layout::LyricSheets,
+        Layout::ChordSheets,
+    ];
+}
+
+impl Display for Layout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Layout::RunningOrder => "Running order",
+                Layout::LyricSheets => "Lyric sheets",
+                Layout::ChordSheets => "Lyric sheets with chords",
+            }
+        )
+    }
+}
+
+// Content of the document, written in HTML or in PDF
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Block {
+    Title(String),
+    Heading(String),
+    Label(String),
+    Line(String),
+    // Chords and the lyrics below them are aligned, in a monospace font
+    Chords(String),
+    Mono(String),
+    // Position, item, key, notes
+    Row([String; 4]),
+    Space,
+}
+
+fn blocks(service: &Service, books: &[Book], layout: Layout, capo: u8) -> Vec<Block> {
+    let name = match service.name.is_empty() {
+        true => String::from("Service"),
+        false => service.name.clone(),
+    };
+    let mut blocks = vec![Block::Title(name)];
+    if layout == Layout::RunningOrder {
+        blocks.push(Block::Row(["#", "Item", "Key", "Notes"].map(String::from)));
+    }
+    for (index, item) in service.iter().enumerate() {
+        let song = match (&item.kind, layout) {
+            (_, Layout::RunningOrder) => {
+                let song = item.as_song();
+                blocks.push(Block::Row([
+                    (index + 1).to_string(),
+                    item.title(books),
+                    song.and_then(|song| song.key(capo)).unwrap_or_default(),
+                    // The comments of the song when the item has no notes
+                    match item.notes.trim() {
+                        "" => song
+                            .and_then(|song| song.comments.clone())
+                            .unwrap_or_default(),
+                        notes => notes.to_string(),
+                    },
+                ]));
+                continue;
+            }
+            (ItemKind::Song(song), _) => song,
+            _ => continue,
+        };
+        blocks.push(Block::Heading(item.title(books)));
+        if layout == Layout::ChordSheets
+            && let Some(key) = song.key(capo)
+        {
+            blocks.push(Block::Label(format!("Key: {}", key)));
+        }
+        for (verse, (label, _)) in song.lyrics.iter().enumerate() {
+            blocks.push(Block::Label(label.to_string()));
+            match layout {
+                Layout::ChordSheets => {
+                    for line in song.get_chords(verse, capo).lines() {
+                        let (chords, lyrics) = chords::split(line);
+                        if !chords.trim().is_empty() {
+                            blocks.push(Block::Chords(chords));
+                        }
+                        blocks.push(Block::Mono(lyrics));
+                    }
+                }
+                _ => blocks.extend(song.get(verse).lines().map(|line| Block::Line(line.into()))),
+            }
+            blocks.push(Block::Space);
+        }
+    }
+    blocks
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn html(blocks: &[Block]) -> String {
+    let mut html = String::from(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<style>\n\
+        body { font-family: sans-serif; margin: 2em; }\n\
+        h2 { margin-top: 1.5em; break-after: avoid; }\n\
+        .label { font-weight: bold; color: #666; margin: 0.5em 0 0 0; }\n\
+        .line { margin: 0; }\n\
+        pre { margin: 0; font-size: 1em; }\n\
+        .chords { font-weight: bold; }\n\
+        table { border-collapse: collapse; width: 100%; }\n\
+        td, th { border-bottom: 1px solid #ccc; padding: 0.3em; text-align: left; }\n\
+        </style>\n",
+    );
+    let mut table = false;
+    for block in blocks {
+        if table && !matches!(block, Block::Row(_)) {
+            html += "</table>\n";
+            table = false;
+        }
+        match block {
+            Block::Title(title) => {
+                let title = escape_html(title);
+                html += &format!(
+                    "<title>{}</title>\n</head>\n<body>\n<h1>{}</h1>\n",
+                    title, title
+                );
+            }
+            Block::Heading(heading) => html += &format!("<h2>{}</h2>\n", escape_html(heading)),
+            Block::Label(label) => {
+                html += &format!("<p class=\"label\">{}</p>\n", escape_html(label))
+            }
+            Block::Line(line) => html += &format!("<p class=\"line\">{}</p>\n", escape_html(line)),
+            Block::Chords(chords) => {
+                html += &format!("<pre class=\"chords\">{}</pre>\n", escape_html(chords))
+            }
+            Block::Mono(line) => html += &format!("<pre>{}</pre>\n", escape_html(line)),
+            Block::Row(cells) => {
+                // The first row is the header
+                let tag = if table { "td" } else { "th" };
+                if !table {
+                    html += "<table>\n";
+                    table = true;
+                }
+                html += "<tr>";
+                for cell in cells {
+                    html += &format!("<{}>{}</{}>", tag, escape_html(cell), tag);
+                }
+                html += "</tr>\n";
+            }
+            Block::Space => (),
+        }
+    }
+    if table {
+        html += "</table>\n";
+    }
+    html + "</body>\n</html>\n"
+}
+
+// A4 in points
+const PAGE_WIDTH: f32 = 595.0;
+const PAGE_HEIGHT: f32 = 842.0;
+const MARGIN: f32 = 50.0;
+// Positions of the columns of the running order
+const COLUMNS: [f32; 4] = [0.0, 30.0, 300.0, 350.0];
+
+// Standard PDF fonts, they don't need to be embedded
+const FONTS: [&str; 4] = ["Helvetica", "Helvetica-Bold", "Courier", "Courier-Bold"];
+
+// Text in a PDF string with the WinAnsi encoding, the other characters are replaced
+fn pdf_text(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for c in text.chars() {
+        let code = match c {
+            '\\' | '(' | ')' => {
+                result.push('\\');
+                c as u32
+            }
+            '\u{20}'..='\u{7e}' | '\u{a0}'..='\u{ff}' => c as u32,
+            '€' => 0x80,
+            '…' => 0x85,
+            '‘' => 0x91,
+            '’' => 0x92,
+            '“' => 0x93,
+            '”' => 0x94,
+            '–' => 0x96,
+            '—' => 0x97,
+            _ => '?' as u32,
+        };
+        match code {
+            0x20..=0x7e => result.push(code as u8 as char),
+            _ => result += &format!("\\{:03o}", code),
+        }
+    }
+    result
+}
+
+// Cut a line to fit the width, the width of the characters is estimated
+fn wrap(text: &str, size: f32, width: f32, mono: bool) -> Vec<String> {
+    let average = if mono { 0.6 } else { 0.5 };
+    let max = ((width / (size * average)) as usize).max(1);
+    if mono {
+        let chars: Vec<char> = text.chars().collect();
+        return match chars.is_empty() {
+            true => vec![String::new()],
+            false => chars
+                .chunks(max)
+                .map(|chunk| chunk.iter().collect())
+                .collect(),
+        };
+    }
+    let mut lines = vec![String::new()];
+    for word in text.split_whitespace() {
+        let last = lines.last_mut().expect("ERROR: No line");
+        if !last.is_empty() && last.chars().count() + 1 + word.chars().count() > max {
+            lines.push(word.to_string());
+        } else {
+            if !last.is_empty() {
+                last.push(' ');
+            }
+            last.push_str(word);
+        }
+    }
+    lines
+}
+
+struct Pdf {
+    pages: Vec<String>,
+    content: String,
+    y: f32,
+}
+
+impl Pdf {
+    fn new() -> Self {
+        Pdf {
+            pages: vec![],
+            content: String::new(),
+            y: PAGE_HEIGHT - MARGIN,
+        }
+    }
+
+    fn new_page(&mut self) {
+        self.pages.push(std::mem::take(&mut self.content));
+        self.y = PAGE_HEIGHT - MARGIN;
+    }
+
+    // Go down, on a new page when the line doesn't fit
+    fn advance(&mut self, height: f32) {
+        if self.y - height < MARGIN {
+            self.new_page();
+        }
+        self.y -= height;
+    }
+
+    fn text(&mut self, font: usize, size: f32, x: f32, text: &str) {
+        self.content += &format!(
+            "BT /F{} {} Tf {:.1} {:.1} Td ({}) Tj ET\n",
+            font + 1,
+            size,
+            MARGIN + x,
+            self.y,
+            pdf_text(text)
+        );
+    }
+
+    fn lines(&mut self, font: usize, size: f32, text: &str) {
+        let mono = font >= 2;
+        for line in wrap(text, size, PAGE_WIDTH - 2.0 * MARGIN, mono) {
+            self.advance(size * 1.3);
+            self.text(font, size, 0.0, &line);
+        }
+    }
+
+    fn write(mut self) -> Vec<u8> {
+        if !self.content.is_empty() || self.pages.is_empty() {
+            self.pages.push(self.content);
+        }
+        let mut objects = vec![
+            String::from("<< /Type /Catalog /Pages 2 0 R >>"),
+            format!(
+                "<< /Type /Pages /Kids [{}] /Count {} >>",
+                (0..self.pages.len())
+                    .map(|page| format!("{} 0 R", 7 + 2 * page))
+                    .collect::<Vec<_>>()
+                    .join(" "),
+                self.pages.len()
+            ),
+        ];
+        for font in FONTS {
+            objects.push(format!(
+                "<< /Type /Font /Subtype /Type1 /BaseFont /{} /Encoding /WinAnsiEncoding >>",
+                font
+            ));
+        }
+        for (page, content) in self.pages.iter().enumerate() {
+            objects.push(format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] \
+                /Resources << /Font << /F1 3 0 R /F2 4 0 R /F3 5 0 R /F4 6 0 R >> >> \
+                /Contents {} 0 R >>",
+                PAGE_WIDTH,
+                PAGE_HEIGHT,
+                8 + 2 * page
+            ));
+            objects.push(format!(
+                "<< /Length {} >>\nstream\n{}endstream",
+                content.len(),
+                content
+            ));
+        }
+        // The cross-reference table gives the position of each object
+        let mut pdf = String::from("%PDF-1.4\n");
+        let mut offsets = Vec::with_capacity(objects.len());
+        for (index, object) in objects.iter().enumerate() {
+            offsets.push(pdf.len());
+            pdf += &format!("{} 0 obj\n{}\nendobj\n", index + 1, object);
+        }
+        let xref = pdf.len();
+        pdf += &format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+        for offset in offsets {
+            pdf += &format!("{:010} 00000 n \n", offset);
+        }
+        pdf += &format!(
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objects.len() + 1,
+            xref
+        );
+        pdf.into_bytes()
+    }
+}
+
+fn pdf(blocks: &[Block]) -> Vec<u8> {
+    let mut pdf = Pdf::new();
+    for block in blocks {
+        match block {
+            Block::Title(title) => pdf.lines(1, 20.0, title),
+            Block::Heading(heading) => {
+                // A song starts on the same page as its title
+                if pdf.y < MARGIN + 150.0 {
+                    pdf.new_page();
+                }
+                pdf.advance(10.0);
+                pdf.lines(1, 14.0, heading);
+            }
+            Block::Label(label) => pdf.lines(1, 9.0, label),
+            Block::Line(line) => pdf.lines(0, 11.0, line),
+            Block::Chords(chords) => pdf.lines(3, 10.0, chords),
+            Block::Mono(line) => pdf.lines(2, 10.0, line),
+            Block::Row(cells) => {
+                pdf.advance(14.0);
+                for (cell, x) in cells.iter().zip(COLUMNS) {
+                    let end = COLUMNS.iter().find(|next| **next > x);
+                    let width = end.unwrap_or(&(PAGE_WIDTH - 2.0 * MARGIN)) - x;
+                    let text = wrap(cell, 10.0, width - 5.0, false).swap_remove(0);
+                    pdf.text(0, 10.0, x, &text);
+                }
+            }
+            Block::Space => pdf.advance(6.0),
+        }
+    }
+    pdf.write()
+}
+
+// The format is given by the extension of the file
+fn export(blocks: &[Block], path: &Path) -> std::io::Result<()> {
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("html" | "htm") => std::fs::write(path, html(blocks)),
+        _ => std::fs::write(path, pdf(blocks)),
+    }
+}
+
+impl App {
+    pub fn print(&self, layout: Layout) -> Task<Message> {
+        let name = match self.service.name.is_empty() {
+            true => format!("{}.pdf", layout),
+            false => format!("{} {}.pdf", self.service.name, layout),
+        };
+        Task::perform(
+            rfd::AsyncFileDialog::new()
+                .set_file_name(name)
+                .add_filter("PDF", &["pdf"])
+                .add_filter("HTML", &["html", "htm"])
+                .save_file(),
+            move |file| file.map(|file| Message::ExportPrint(layout, file.path().to_path_buf())),
+        )
+        .and_then(Task::done)
+    }
+
+    pub fn export_print(&self, layout: Layout, path: &Path) {
+        let blocks = blocks(&self.service, &self.books, layout, self.set.capo);
+        if let Err(e) = export(&blocks, path) {
+            println!("ERROR: Failed to print the service: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn pdf_xref() {
+        let blocks = [
+            Block::Title(String::from("Sunday (morning)")),
+            Block::Line(String::from("Amazing grace — how sweet")),
+        ];
+        let pdf = String::from_utf8(pdf(&blocks)).unwrap();
+        assert!(pdf.contains("(Sunday \\(morning\\)) Tj"));
+        assert!(pdf.contains("(Amazing grace \\227 how sweet) Tj"));
+        // Each offset of the cross-reference table points to its object
+        let xref = pdf.find("xref\n").unwrap();
+        for (index, line) in pdf[xref..].lines().skip(3).take(8).enumerate() {
+            let offset: usize = line[..10].parse().unwrap();
+            assert!(pdf[offset..].starts_with(&format!("{} 0 obj", index + 1)));
+        }
+        let startxref = pdf.rsplit("startxref\n").next().unwrap();
+        assert_eq!(
+            startxref.lines().next().unwrap().parse::<usize>().unwrap(),
+            xref
+        );
+    }
+}
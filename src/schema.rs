@@ -0,0 +1,113 @@
+use rusqlite::{Connection, Result};
+
+// Each migration is applied once, in order, and then recorded in user_version
+const MIGRATIONS: &[&str] = &[
+    // 1: ChordPro subtitle and comments
+    "ALTER TABLE songs ADD COLUMN alternate_title VARCHAR(255);
+    ALTER TABLE songs ADD COLUMN comments TEXT;",
+    // 2: OpenLP metadata
+    "ALTER TABLE songs ADD COLUMN verse_order VARCHAR(128);
+    ALTER TABLE songs ADD COLUMN copyright VARCHAR(255);
+    ALTER TABLE songs ADD COLUMN ccli_number VARCHAR(64);
+    CREATE TABLE topics (
+            id   INTEGER PRIMARY KEY,
+            name VARCHAR(128)
+    );
+    CREATE TABLE songs_topics (
+            song_id  INTEGER NOT NULL,
+            topic_id INTEGER NOT NULL
+    );",
+    // 3: Origin of imported songs
+    "ALTER TABLE songs ADD COLUMN source VARCHAR(255);",
+    // 4: Songbook metadata
+    "ALTER TABLE books ADD COLUMN publisher VARCHAR(255);
+    ALTER TABLE books ADD COLUMN abbreviation VARCHAR(32);",
+    // 5: Songs projected, for the CCLI reports
+    "CREATE TABLE song_usage (
+            id      INTEGER PRIMARY KEY,
+            song_id INTEGER NOT NULL,
+            used_at DATETIME NOT NULL,
+            service VARCHAR(255)
+    );",
+    // 6: Service plans, the templates have no date
+    "CREATE TABLE plans (
+            id   INTEGER PRIMARY KEY,
+            name VARCHAR(255) NOT NULL,
+            date DATE
+    );
+    CREATE TABLE plan_items (
+            plan_id  INTEGER NOT NULL,
+            position INTEGER NOT NULL,
+            slot     VARCHAR(255),
+            song_id  INTEGER
+    );",
+    // 7: Timers of the plans, like "until 600 advance"
+    "ALTER TABLE plan_items ADD COLUMN timer VARCHAR(64);",
+    // 8: Seconds between the slides of the loops
+    "ALTER TABLE plan_items ADD COLUMN interval INTEGER NOT NULL DEFAULT 0;",
+    // 9: Videos and audio files, like "video 100 background /path/to/file"
+    "ALTER TABLE plan_items ADD COLUMN media VARCHAR(1024);",
+    // 10: Backgrounds, a color like "#000000" or an image of the backgrounds folder
+    "ALTER TABLE songs ADD COLUMN background VARCHAR(255);
+    ALTER TABLE plan_items ADD COLUMN background VARCHAR(255);",
+    // 11: Notes of the service items
+    "ALTER TABLE plan_items ADD COLUMN notes TEXT NOT NULL DEFAULT '';",
+    // 12: Planned seconds of the items, and the items shown during the services
+    "ALTER TABLE plan_items ADD COLUMN duration INTEGER NOT NULL DEFAULT 0;
+    CREATE TABLE service_runs (
+            id         INTEGER PRIMARY KEY,
+            service    VARCHAR(255),
+            started_at DATETIME NOT NULL,
+            ended_at   DATETIME
+    );
+    CREATE TABLE service_log (
+            run_id     INTEGER NOT NULL,
+            position   INTEGER NOT NULL,
+            title      VARCHAR(255),
+            planned    INTEGER NOT NULL DEFAULT 0,
+            started_at DATETIME NOT NULL
+    );",
+];
+
+pub fn path() -> String {
+    format!(
+        "{}/Documents/songs.sqlite",
+        std::env::var("HOME").unwrap_or_default()
+    )
+}
+
+pub fn open() -> Result<Connection> {
+    let db = Connection::open(path())?;
+    init(&db)?;
+    Ok(db)
+}
+
+pub fn init(db: &Connection) -> Result<()> {
+    db.execute_batch(
+        "CREATE TABLE IF NOT EXISTS songs (
+                id     INTEGER PRIMARY KEY AUTOINCREMENT,
+                title  VARCHAR(255),
+                lyrics TEXT NOT NULL,
+                book   INTEGER,
+                number INTEGER
+        );
+        CREATE TABLE IF NOT EXISTS authors (
+                id     INTEGER PRIMARY KEY,
+                name   VARCHAR(255)
+        );
+        CREATE TABLE IF NOT EXISTS authors_songs (
+                author_id INTEGER NOT NULL,
+                song_id   INTEGER NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS books (
+                id   INTEGER PRIMARY KEY,
+                name VARCHAR(255)
+        );",
+    )?;
+    let version: usize = db.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        db.execute_batch(migration)?;
+        db.pragma_update(None, "user_version", index + 1)?;
+    }
+    Ok(())
+}
@@ -1,17 +1,28 @@
 use iced::{
     Element,
-    widget::{column, slider, toggler},
+    widget::{button, column, pick_list, row, slider, text_input, toggler},
 };
 use serde::{Deserialize, Serialize};
 
-use crate::{App, Message, widget::ttext};
+use crate::{App, Message, style, widget::ttext};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Settings {
     pub font_size: f32,
     pub spacing: f32,
     pub debug_layout: bool,
     pub dark_theme: bool,
+    pub capo: u8,
+    // Footer of the display: {title}, {authors}, {copyright}, {ccli} (song number), {licence}
+    pub footer: String,
+    pub footer_visibility: FooterVisibility,
+    pub ccli_licence: String,
+    // Lines per slide at most, 0 for no limit
+    pub max_lines: u8,
+    pub slide_view: SlideView,
+    // Image of the logo screen, the default background if empty
+    pub logo: String,
 }
 
 impl Default for Settings {
@@ -21,10 +32,85 @@ impl Default for Settings {
             spacing: 2.0,
             debug_layout: false,
             dark_theme: true,
+            capo: 0,
+            footer: String::from("{title} — {authors} © {copyright} CCLI {licence}"),
+            footer_visibility: FooterVisibility::default(),
+            ccli_licence: String::new(),
+            max_lines: 0,
+            slide_view: SlideView::default(),
+            logo: String::new(),
         }
     }
 }
 
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FooterVisibility {
+    Always,
+    #[default]
+    FirstSlide,
+    LastSlide,
+    FirstAndLastSlides,
+    Never,
+}
+
+impl FooterVisibility {
+    pub const ALL: [FooterVisibility; 5] = [
+        FooterVisibility::Always,
+        FooterVisibility::FirstSlide,
+        FooterVisibility::LastSlide,
+        FooterVisibility::FirstAndLastSlides,
+        FooterVisibility::Never,
+    ];
+
+    pub fn is_visible(&self, slide: usize, slides: usize) -> bool {
+        let last = slide + 1 >= slides;
+        match self {
+            FooterVisibility::Always => true,
+            FooterVisibility::FirstSlide => slide == 0,
+            FooterVisibility::LastSlide => last,
+            FooterVisibility::FirstAndLastSlides => slide == 0 || last,
+            FooterVisibility::Never => false,
+        }
+    }
+}
+
+impl std::fmt::Display for FooterVisibility {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let visibility = match self {
+            FooterVisibility::Always => "Always",
+            FooterVisibility::FirstSlide => "First slide",
+            FooterVisibility::LastSlide => "Last slide",
+            FooterVisibility::FirstAndLastSlides => "First and last slides",
+            FooterVisibility::Never => "Never",
+        };
+        write!(f, "{}", visibility)
+    }
+}
+
+// Slides of the preview and live panes: their text, or miniatures of the display
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SlideView {
+    #[default]
+    Text,
+    List,
+    Grid,
+}
+
+impl SlideView {
+    pub const ALL: [SlideView; 3] = [SlideView::Text, SlideView::List, SlideView::Grid];
+}
+
+impl std::fmt::Display for SlideView {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let view = match self {
+            SlideView::Text => "Text",
+            SlideView::List => "List",
+            SlideView::Grid => "Grid",
+        };
+        write!(f, "{}", view)
+    }
+}
+
 impl App {
     pub fn view_settings(&self) -> Element<'_, Message> {
         let set = &self.set;
@@ -46,12 +132,60 @@ impl App {
             .size(set.font_size)
             .text_size(set.font_size);
 
+        let footer_header = ttext(
+            "Footer: {title} {authors} {copyright} {ccli} {licence}",
+            self,
+        );
+        let footer = text_input("Footer", &set.footer)
+            .on_input(Message::FooterChanged)
+            .size(set.font_size);
+        let footer_visibility = pick_list(
+            FooterVisibility::ALL,
+            Some(set.footer_visibility),
+            Message::FooterVisibilityChanged,
+        )
+        .text_size(set.font_size)
+        .style(style::theme_pick_list);
+
+        let max_lines_header = ttext(
+            match set.max_lines {
+                0 => String::from("Lines per slide: no limit"),
+                max_lines => format!("Lines per slide: {}", max_lines),
+            },
+            self,
+        );
+        let max_lines = slider(0..=16, set.max_lines, Message::MaxLinesChanged)
+            .on_release(Message::SplitSlides);
+
+        let logo_header = ttext("Logo or announcement image", self);
+        let logo = row![
+            text_input("Image file", &set.logo)
+                .on_input(Message::LogoChanged)
+                .size(set.font_size),
+            button(ttext("Browse", self)).on_press(Message::PickLogo),
+        ]
+        .spacing(set.spacing);
+
+        let licence_header = ttext("CCLI licence", self);
+        let licence = text_input("CCLI licence number", &set.ccli_licence)
+            .on_input(Message::LicenceChanged)
+            .size(set.font_size);
+
         let settings = column![
             theme,
             font_size_header,
             font_size_slidder,
             spacing_header,
             spacing_slider,
+            footer_header,
+            footer,
+            footer_visibility,
+            max_lines_header,
+            max_lines,
+            logo_header,
+            logo,
+            licence_header,
+            licence,
             debug_layout,
         ];
         Element::from(settings)
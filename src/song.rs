@@ -2,6 +2,11 @@ use quick_xml::{Reader, events::Event};
 use rusqlite::Row;
 use std::fmt::Display;
 
+use crate::chords;
+
+// Line of the lyrics starting a new slide, like in OpenLP
+pub const SLIDE_BREAK: &str = "[---]";
+
 #[derive(Debug, Default, Clone, PartialEq, Eq)]
 pub struct Song {
     pub id: u16,
@@ -9,11 +14,21 @@ pub struct Song {
     pub lyrics: Vec<(Verse, String)>,
     pub book: Option<u16>,
     pub number: Option<u16>,
+    pub alternate_title: Option<String>,
+    pub comments: Option<String>,
+    pub verse_order: Option<String>,
+    pub copyright: Option<String>,
+    pub ccli_number: Option<String>,
+    // Only loaded from the library, the files give the authors apart
+    pub authors: Vec<String>,
+    // Color or image of the backgrounds folder, None for the default
+    pub background: Option<String>,
     pub current: usize,
+    pub transpose: i8,
 }
 
 impl Song {
-    fn parse_lyrics(string: String) -> Result<Vec<(Verse, String)>, quick_xml::Error> {
+    pub fn parse_lyrics(string: String) -> Result<Vec<(Verse, String)>, quick_xml::Error> {
         let mut lyrics = vec![];
         let (mut verse, mut nb, mut txt) = (VerseType::Other, 0, String::new());
         let mut reader = Reader::from_reader(string.as_bytes());
@@ -54,7 +69,7 @@ impl Song {
                     }
                 }
                 Ok(Event::CData(raw)) => {
-                    txt = raw.decode()?.to_string();
+                    txt = chords::normalize(&raw.decode()?);
                 }
                 Ok(Event::End(element)) if element.name().as_ref() == b"verse" => {
                     lyrics.push((Verse::new(verse, nb), txt));
@@ -66,26 +81,97 @@ impl Song {
         Ok(lyrics)
     }
 
-    pub fn book(&self, books: &Vec<Book>) -> String {
-        if self.book.is_none() {
-            return String::new();
+    // Serialize the lyrics in the OpenLP format
+    pub fn lyrics_xml(&self) -> String {
+        let mut xml =
+            String::from("<?xml version='1.0' encoding='UTF-8'?>\n<song version=\"1.0\"><lyrics>");
+        for (verse, text) in &self.lyrics {
+            xml += &format!(
+                "<verse type=\"{}\" label=\"{}\"><![CDATA[{}]]></verse>",
+                verse.0.tag(),
+                verse.1,
+                text.replace("]]>", "]]]]><![CDATA[>")
+            );
+        }
+        xml += "</lyrics></song>";
+        xml
+    }
+
+    // Repeat the verses following the verse order, like "v1 c1 v2 c1"
+    pub fn arranged(mut self) -> Self {
+        if let Some(order) = &self.verse_order {
+            let lyrics: Vec<(Verse, String)> = order
+                .split_whitespace()
+                .filter_map(Verse::from_label)
+                .filter_map(|verse| self.lyrics.iter().find(|(v, _)| *v == verse).cloned())
+                .collect();
+            if !lyrics.is_empty() {
+                self.lyrics = lyrics;
+            }
         }
-        let id = self.book.unwrap();
-        let book = books
+        self
+    }
+
+    // Cut the verses in slides at the breaks, and to have at most max_lines lines (0 for no limit)
+    pub fn split_slides(mut self, max_lines: usize) -> Self {
+        let mut slides = Vec::with_capacity(self.lyrics.len());
+        for (verse, text) in self.lyrics {
+            let parts = split_verse(&text, max_lines);
+            if parts.len() == 1 {
+                slides.extend(parts.into_iter().map(|part| (verse, part)));
+            } else {
+                // The parts past 255 keep the last number
+                slides.extend(parts.into_iter().enumerate().map(|(part, text)| {
+                    let part = u8::try_from(part + 1).unwrap_or(u8::MAX);
+                    (Verse(verse.0, verse.1, part), text)
+                }));
+            }
+        }
+        self.lyrics = slides;
+        self
+    }
+
+    // Add a verse, the verses without number are numbered after the previous ones
+    pub fn push_verse(&mut self, verse: Option<Verse>, text: &mut String) {
+        let lyrics = text.trim_matches('\n').to_string();
+        text.clear();
+        if lyrics.trim().is_empty() {
+            return;
+        }
+        let verse = verse.unwrap_or(Verse(VerseType::Verse, 0, 0));
+        let verse = if verse.1 == 0 {
+            let count = self
+                .lyrics
+                .iter()
+                .filter(|(other, _)| other.0 == verse.0)
+                .count();
+            Verse(verse.0, count as u8 + 1, 0)
+        } else {
+            verse
+        };
+        self.lyrics.push((verse, lyrics));
+    }
+
+    pub fn book(&self, books: &[Book]) -> String {
+        let Some(id) = self.book else {
+            return String::new();
+        };
+        // The book may have been deleted since the song was loaded
+        books
             .iter()
             .find(|book| book.id == id)
-            .expect("ERROR: failed to find book");
-        book.clone().name
+            .map(|book| book.name.clone())
+            .unwrap_or_default()
     }
 
-    pub fn title(&self, books: &Vec<Book>) -> String {
+    pub fn title(&self, books: &[Book]) -> String {
         let mut title = String::new();
         title += &self.book(books);
-        if self.number.is_some() {
+        if let Some(number) = self.number {
             if !title.is_empty() {
                 title += " ";
             }
-            title += &format!("{:03}", self.number.unwrap());
+            title += &format!("{:03}", number);
         }
         if !title.is_empty() {
             title += "  ";
@@ -112,6 +198,14 @@ impl Song {
         }
     }
 
+    // Back to the first slide after the last one, for the loops
+    pub fn set_next_wrapping(&mut self) {
+        match self.current + 1 < self.lyrics.len() {
+            true => self.current += 1,
+            false => self.current = 0,
+        }
+    }
+
     pub fn set_next_chorus(&mut self) {
         self.set_next_type(VerseType::Chorus);
     }
@@ -122,30 +216,58 @@ impl Song {
 
     // Go to the next verse of vtype even if it is before current position
     fn set_next_type(&mut self, vtype: VerseType) {
-        let lyrics = &self.lyrics;
+        // The first slide of the verse
+        let is_vtype = |id: &usize| self.lyrics[*id].0.0 == vtype && self.lyrics[*id].0.2 <= 1;
         // End of the song
-        for id in self.current + 1..self.lyrics.len() {
-            if lyrics[id].0.0 == vtype {
-                self.current = id;
-                return;
-            }
-        }
+        if let Some(id) = (self.current + 1..self.lyrics.len()).find(is_vtype) {
+            self.current = id;
         // Continue at the beginning if not found in the end
-        for id in 0..self.current {
-            if lyrics[id].0.0 == vtype {
-                self.current = id;
-                return;
-            }
+        } else if let Some(id) = (0..self.current).find(is_vtype) {
+            self.current = id;
         }
     }
 
+    pub fn transpose(&mut self, semitones: i8) {
+        self.transpose = (self.transpose + semitones) % 12;
+    }
+
+    // Lyrics without chords, for the congregation
     pub fn get(&self, index: usize) -> String {
         if index < self.lyrics.len() {
-            self.lyrics[index].1.to_string()
+            chords::strip(&self.lyrics[index].1)
+        } else {
+            String::new()
+        }
+    }
+
+    // Lyrics with the chords transposed for the musicians
+    pub fn get_chords(&self, index: usize, capo: u8) -> String {
+        if index < self.lyrics.len() {
+            chords::transpose_text(&self.lyrics[index].1, self.transpose - capo as i8)
         } else {
             String::new()
         }
     }
+
+    // The first chord gives the key, transposed like the chords shown
+    pub fn key(&self, capo: u8) -> Option<String> {
+        let chord = self.lyrics.iter().find_map(|(_, text)| {
+            let start = text.find('[')?;
+            let end = text[start..].find(']')?;
+            Some(&text[start + 1..start + end])
+        })?;
+        let chord = chord.split('/').next().unwrap_or(chord);
+        // Only the root and the minor of the chord
+        let root = chord
+            .char_indices()
+            .skip(1)
+            .find(|(_, c)| !matches!(c, '#' | 'b'))
+            .map_or(chord.len(), |(index, _)| index);
+        let quality = &chord[root..];
+        let minor = quality.starts_with('m') && !quality.starts_with("maj");
+        let key = chords::transpose(&chord[..root], self.transpose - capo as i8);
+        Some(if minor { key + "m" } else { key })
+    }
 }
 
 impl TryFrom<&Row<'_>> for Song {
@@ -158,7 +280,15 @@ impl TryFrom<&Row<'_>> for Song {
                 .expect("ERROR: Failed to parse lyrics"),
             book: value.get(3)?,
             number: value.get(4)?,
+            alternate_title: value.get(5)?,
+            comments: value.get(6)?,
+            verse_order: value.get(7)?,
+            copyright: value.get(8)?,
+            ccli_number: value.get(9)?,
+            authors: vec![],
+            background: value.get(10)?,
             current: 0,
+            transpose: 0,
         })
     }
 }
@@ -167,6 +297,8 @@ impl TryFrom<&Row<'_>> for Song {
 pub struct Book {
     pub id: u16,
     pub name: String,
+    pub publisher: Option<String>,
+    pub abbreviation: Option<String>,
 }
 
 impl std::fmt::Display for Book {
@@ -181,26 +313,97 @@ impl TryFrom<&Row<'_>> for Book {
         Ok(Book {
             id: value.get(0)?,
             name: value.get(1)?,
+            publisher: value.get(2)?,
+            abbreviation: value.get(3)?,
         })
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub struct Verse(VerseType, u8);
+// The part is the slide of a verse cut in several ones, 0 for a whole verse
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Verse(VerseType, u8, u8);
 
 impl Verse {
     pub fn new(versetype: VerseType, nb: u8) -> Self {
-        Self(versetype, nb)
+        Self(versetype, nb, 0)
+    }
+
+    pub fn kind(&self) -> VerseType {
+        self.0
+    }
+
+    pub fn number(&self) -> u8 {
+        self.1
+    }
+
+    // Read a heading like "Verse 2", "Chorus", "Couplet 1" or "V1"
+    pub fn from_label(label: &str) -> Option<Self> {
+        let label = label.trim().trim_end_matches(':').to_lowercase();
+        let split = label
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(label.len());
+        let (name, number) = label.split_at(split);
+        let number = match number.trim() {
+            "" => 1,
+            number => number.parse().ok()?,
+        };
+        // English, French, German, Spanish, Portuguese, Italian and Dutch names
+        let versetype = match name.trim() {
+            "i" | "intro" | "introduction" | "introducción" | "introdução" => VerseType::Intro,
+            "v" | "verse" | "couplet" | "strophe" | "vers" | "verso" | "estrofa" | "strofa" => {
+                VerseType::Verse
+            }
+            "p" | "pre-chorus" | "prechorus" | "pre chorus" | "pré-refrain" | "pre-refrain"
+            | "prérefrain" | "vorrefrain" | "pre-coro" | "precoro" | "pré-refrão" => {
+                VerseType::PreChorus
+            }
+            "c" | "chorus" | "refrain" | "refrein" | "coro" | "estribillo" | "refrão"
+            | "ritornello" => VerseType::Chorus,
+            "b" | "bridge" | "pont" | "puente" | "ponte" | "brug" => VerseType::Bridge,
+            "e" | "end" | "ending" | "outro" | "fin" | "final" | "coda" | "schluss" => {
+                VerseType::End
+            }
+            "o" | "other" | "tag" | "interlude" | "instrumental" | "autre" => VerseType::Other,
+            _ => return None,
+        };
+        Some(Self(versetype, number, 0))
     }
 }
 
 impl Display for Verse {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}{}", self.0, self.1)
+        write!(f, "{}{}", self.0, self.1)?;
+        // V1a, V1b… then V1.27, V1.28… past z
+        match self.2 {
+            0 => Ok(()),
+            part @ 1..=26 => write!(f, "{}", (b'a' + part - 1) as char),
+            part => write!(f, ".{}", part),
+        }
+    }
+}
+
+// The lines are shared evenly: 12 lines with 8 lines at most give two slides of 6 lines
+fn split_verse(text: &str, max_lines: usize) -> Vec<String> {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut parts = vec![];
+    for part in lines.split(|line| line.trim() == SLIDE_BREAK) {
+        if part.iter().all(|line| line.trim().is_empty()) {
+            continue;
+        }
+        let count = match max_lines {
+            0 => 1,
+            max_lines => part.len().div_ceil(max_lines),
+        };
+        let size = part.len().div_ceil(count);
+        parts.extend(part.chunks(size).map(|lines| lines.join("\n")));
+    }
+    if parts.is_empty() {
+        parts.push(String::new());
     }
+    parts
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum VerseType {
     Intro,
     Verse,
@@ -229,6 +432,33 @@ impl Display for VerseType {
     }
 }
 
+impl VerseType {
+    // Type attribute of the OpenLP format
+    pub fn tag(&self) -> &'static str {
+        match self {
+            VerseType::Intro => "i",
+            VerseType::Verse => "v",
+            VerseType::PreChorus => "p",
+            VerseType::Chorus => "c",
+            VerseType::Bridge => "b",
+            VerseType::End => "e",
+            VerseType::Other => "o",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            VerseType::Intro => "Intro",
+            VerseType::Verse => "Verse",
+            VerseType::PreChorus => "Pre-Chorus",
+            VerseType::Chorus => "Chorus",
+            VerseType::Bridge => "Bridge",
+            VerseType::End => "Ending",
+            VerseType::Other => "Other",
+        }
+    }
+}
+
 impl TryFrom<&str> for VerseType {
     type Error = ();
     fn try_from(value: &str) -> std::result::Result<Self, Self::Error> {
@@ -244,3 +474,37 @@ impl TryFrom<&str> for VerseType {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn slides() {
+        let mut song = Song::default();
+        let mut text = (1..=12)
+            .map(|i| format!("Line {}\n", i))
+            .collect::<String>();
+        song.push_verse(None, &mut text);
+        let mut text = String::from("Chorus 1\n[---]\nChorus 2");
+        song.push_verse(Verse::from_label("C"), &mut text);
+        let song = song.split_slides(8);
+        let labels: Vec<String> = song.lyrics.iter().map(|(v, _)| v.to_string()).collect();
+        assert_eq!(labels, ["V1a", "V1b", "C1a", "C1b"]);
+        assert_eq!(song.lyrics[1].1.lines().count(), 6);
+        assert_eq!(song.lyrics[3].1, "Chorus 2");
+        // Without limit only the breaks cut the verses
+        let song = song.split_slides(0);
+        assert_eq!(song.lyrics.len(), 4);
+        // Past z the parts are numbered
+        let mut song = Song::default();
+        let mut text = (1..=300)
+            .map(|i| format!("Line {}\n", i))
+            .collect::<String>();
+        song.push_verse(None, &mut text);
+        let song = song.split_slides(1);
+        let labels: Vec<String> = song.lyrics.iter().map(|(v, _)| v.to_string()).collect();
+        assert_eq!(labels[25..27], ["V1z", "V1.27"]);
+        assert_eq!(labels[299], "V1.255");
+    }
+}
@@ -0,0 +1,90 @@
+use iced::{
+    Element, Font, Length,
+    alignment::Vertical,
+    widget::{Column, button, horizontal_rule, horizontal_space, pick_list, row, text},
+};
+
+use crate::{
+    App, Message, chords,
+    control::Content,
+    song::Song,
+    style,
+    widget::{BOLD, ttext},
+};
+
+const CHORDS: Font = Font {
+    weight: iced::font::Weight::Bold,
+    ..Font::MONOSPACE
+};
+const CAPOS: [u8; 8] = [0, 1, 2, 3, 4, 5, 6, 7];
+
+impl App {
+    // Musician view: current and next slides of the live song with the chords above the lyrics
+    pub fn view_stage(&self) -> Element<'_, Message> {
+        let size = self.set.font_size * 1.5;
+        let mut stage = Column::new().spacing(self.set.spacing * 4.0).padding(5);
+        // Cues of the item for the musicians, also between the songs
+        let notes = self
+            .service
+            .current_item()
+            .filter(|item| !item.notes.is_empty())
+            .map(|item| {
+                ttext(item.notes.as_str(), self)
+                    .size(size)
+                    .font(BOLD)
+                    .style(style::chord_text)
+            });
+        let Some(song) = self.service.current_song(Content::Direct) else {
+            if let Some(notes) = notes {
+                stage = stage.push(notes);
+            }
+            return stage
+                .push(ttext("No song selected", self).width(Length::Fill).center())
+                .into();
+        };
+        let header = row![
+            ttext(song.title(&self.books), self).font(BOLD),
+            horizontal_space(),
+            ttext("Transpose", self),
+            button(ttext("-", self)).on_press(Message::Transpose(-1)),
+            ttext(format!("{:+}", song.transpose), self),
+            button(ttext("+", self)).on_press(Message::Transpose(1)),
+            ttext("Capo", self),
+            pick_list(CAPOS, Some(self.set.capo), Message::CapoChanged)
+                .text_size(self.set.font_size)
+                .style(style::theme_pick_list),
+        ]
+        .spacing(self.set.spacing)
+        .align_y(Vertical::Center);
+        stage = stage.push(header);
+        if let Some(notes) = notes {
+            stage = stage.push(notes);
+        }
+        stage
+            .push(horizontal_rule(2))
+            .push(self.view_stage_slide(song, song.current, size))
+            .push(horizontal_rule(2))
+            .push(self.view_stage_slide(song, song.current + 1, size * 0.75))
+            .into()
+    }
+
+    fn view_stage_slide(&self, song: &Song, index: usize, size: f32) -> Column<'_, Message> {
+        let mut slide = Column::new();
+        if let Some((verse, _)) = song.lyrics.get(index) {
+            slide = slide.push(ttext(verse.to_string(), self).style(style::soft_text));
+        }
+        for line in song.get_chords(index, self.set.capo).lines() {
+            let (chords, lyrics) = chords::split(line);
+            if !chords.is_empty() {
+                slide = slide.push(
+                    text(chords)
+                        .size(size)
+                        .font(CHORDS)
+                        .style(style::chord_text),
+                );
+            }
+            slide = slide.push(text(lyrics).size(size).font(Font::MONOSPACE));
+        }
+        slide
+    }
+}
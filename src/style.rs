@@ -54,6 +54,12 @@ pub fn soft_text(theme: &Theme) -> widget::text::Style {
     }
 }
 
+pub fn chord_text(theme: &Theme) -> widget::text::Style {
+    widget::text::Style {
+        color: Some(theme.palette().primary),
+    }
+}
+
 pub fn soft_rule(theme: &Theme) -> widget::rule::Style {
     widget::rule::Style {
         color: soft(theme),
@@ -0,0 +1,376 @@
+// Countdown and clock items of the service
+use iced::{
+    Length,
+    widget::{Column, button, column, pick_list, row, text_input, toggler},
+};
+use std::{
+    fmt::Display,
+    time::{Duration, Instant, SystemTime},
+};
+
+use crate::{App, Message, db::ItemKind, style, widget::ttext};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum TimerMode {
+    // Countdown to a time of the day
+    #[default]
+    Until,
+    Duration,
+    Clock,
+}
+
+impl TimerMode {
+    pub const ALL: [TimerMode; 3] = [TimerMode::Until, TimerMode::Duration, TimerMode::Clock];
+}
+
+impl Display for TimerMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mode = match self {
+            TimerMode::Until => "Countdown to",
+            TimerMode::Duration => "Countdown for",
+            TimerMode::Clock => "Clock",
+        };
+        write!(f, "{}", mode)
+    }
+}
+
+// Once at zero the timer counts up
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Timer {
+    pub mode: TimerMode,
+    // Time of the day or duration
+    pub minutes: u32,
+    // Go to the next item of the service at zero
+    pub auto_advance: bool,
+    // Shown in a corner of the display over the next items
+    pub overlay: bool,
+    // Seconds to count, fixed when started
+    total: i64,
+    elapsed: Duration,
+    running: Option<Instant>,
+}
+
+impl Default for Timer {
+    fn default() -> Self {
+        Timer {
+            mode: TimerMode::Until,
+            minutes: 10 * 60,
+            auto_advance: false,
+            overlay: false,
+            total: 0,
+            elapsed: Duration::ZERO,
+            running: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TAction {
+    Mode(TimerMode),
+    // Edited then applied
+    Minutes(String),
+    ApplyMinutes,
+    Start,
+    Pause,
+    Reset,
+    AutoAdvance(bool),
+    Overlay(bool),
+}
+
+// Seconds since midnight, local time
+pub fn clock(utc_offset: i64) -> i64 {
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64;
+    (now + utc_offset).rem_euclid(24 * 3600)
+}
+
+fn hours_minutes(minutes: u32) -> String {
+    format!("{:02}:{:02}", minutes / 60, minutes % 60)
+}
+
+// "10:00" for a time of the day, "5" or "1:30" for a duration
+fn parse_minutes(text: &str) -> Option<u32> {
+    match text.trim().split_once(':') {
+        Some((hours, minutes)) => {
+            Some(hours.trim().parse::<u32>().ok()? * 60 + minutes.trim().parse::<u32>().ok()?)
+        }
+        None => text.trim().parse().ok(),
+    }
+}
+
+impl Timer {
+    fn seconds(&self, clock: i64) -> i64 {
+        match self.mode {
+            TimerMode::Until => self.minutes as i64 * 60 - clock,
+            TimerMode::Duration => self.minutes as i64 * 60,
+            TimerMode::Clock => 0,
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.is_some()
+    }
+
+    fn is_started(&self) -> bool {
+        self.running.is_some() || !self.elapsed.is_zero()
+    }
+
+    pub fn start(&mut self, clock: i64) {
+        if self.running.is_some() {
+            return;
+        }
+        if !self.is_started() {
+            self.total = self.seconds(clock);
+        }
+        self.running = Some(Instant::now());
+    }
+
+    pub fn pause(&mut self) {
+        if let Some(since) = self.running.take() {
+            self.elapsed += since.elapsed();
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.running = None;
+        self.elapsed = Duration::ZERO;
+    }
+
+    // Seconds left, negative once passed
+    pub fn remaining(&self, clock: i64) -> i64 {
+        if !self.is_started() {
+            return self.seconds(clock);
+        }
+        let elapsed = self.elapsed + self.running.map_or(Duration::ZERO, |since| since.elapsed());
+        self.total - elapsed.as_secs() as i64
+    }
+
+    pub fn text(&self, clock: i64) -> String {
+        let seconds = match self.mode {
+            TimerMode::Clock => clock,
+            _ => self.remaining(clock),
+        };
+        let (sign, seconds) = match seconds < 0 {
+            true => ("+", -seconds),
+            false => ("", seconds),
+        };
+        match (self.mode, seconds / 3600) {
+            (TimerMode::Clock, hours) => {
+                format!("{:02}:{:02}:{:02}", hours, seconds / 60 % 60, seconds % 60)
+            }
+            (_, 0) => format!("{}{:02}:{:02}", sign, seconds / 60, seconds % 60),
+            (_, hours) => format!(
+                "{}{}:{:02}:{:02}",
+                sign,
+                hours,
+                seconds / 60 % 60,
+                seconds % 60
+            ),
+        }
+    }
+
+    pub fn title(&self) -> String {
+        match self.mode {
+            TimerMode::Until => format!("Countdown to {}", hours_minutes(self.minutes)),
+            TimerMode::Duration => format!("Countdown for {} min", self.minutes),
+            TimerMode::Clock => String::from("Clock"),
+        }
+    }
+
+    // Saved in the plans and the autosave as "until 600 advance overlay"
+    pub fn spec(&self) -> String {
+        let mut spec = format!(
+            "{} {}",
+            match self.mode {
+                TimerMode::Until => "until",
+                TimerMode::Duration => "duration",
+                TimerMode::Clock => "clock",
+            },
+            self.minutes
+        );
+        if self.auto_advance {
+            spec += " advance";
+        }
+        if self.overlay {
+            spec += " overlay";
+        }
+        spec
+    }
+
+    pub fn from_spec(spec: &str) -> Option<Self> {
+        let mut words = spec.split_whitespace();
+        let mode = match words.next()? {
+            "until" => TimerMode::Until,
+            "duration" => TimerMode::Duration,
+            "clock" => TimerMode::Clock,
+            _ => return None,
+        };
+        let minutes = words.next()?.parse().ok()?;
+        let flags: Vec<&str> = words.collect();
+        Some(Timer {
+            mode,
+            minutes,
+            auto_advance: flags.contains(&"advance"),
+            overlay: flags.contains(&"overlay"),
+            ..Default::default()
+        })
+    }
+}
+
+impl App {
+    pub fn clock(&self) -> i64 {
+        clock(self.utc_offset)
+    }
+
+    pub fn timer(&mut self, index: usize, taction: TAction) {
+        let clock = self.clock();
+        let Some(timer) = self.service.timer_mut(index) else {
+            return;
+        };
+        match taction {
+            TAction::Mode(mode) => {
+                timer.mode = mode;
+                timer.minutes = match mode {
+                    TimerMode::Until => 10 * 60,
+                    _ => 5,
+                };
+                timer.reset();
+                self.timer_input = None;
+            }
+            TAction::Minutes(minutes) => self.timer_input = Some(minutes),
+            TAction::ApplyMinutes => {
+                if let Some(minutes) = self.timer_input.take().as_deref().and_then(parse_minutes) {
+                    timer.minutes = minutes;
+                    timer.reset();
+                }
+            }
+            TAction::Start => timer.start(clock),
+            TAction::Pause => timer.pause(),
+            TAction::Reset => timer.reset(),
+            TAction::AutoAdvance(auto_advance) => timer.auto_advance = auto_advance,
+            TAction::Overlay(overlay) => timer.overlay = overlay,
+        }
+    }
+
+    // Each second: the timer at zero goes to the next item, if there is one
+    pub fn tick(&mut self) {
+        let clock = self.clock();
+        let Some(current) = self.service.current_song_index() else {
+            return;
+        };
+        if let Some(timer) = self.service.timer_mut(current)
+            && timer.auto_advance
+            && timer.is_running()
+            && timer.mode != TimerMode::Clock
+            && timer.remaining(clock) <= 0
+        {
+            timer.pause();
+            if current + 1 < self.service.len() {
+                self.service.set_current_song(current + 1);
+                self.start_item(current + 1);
+                self.record_usage();
+            }
+        }
+    }
+
+    pub fn view_timer(&self, index: usize, timer: &Timer) -> Column<'_, Message> {
+        let action = move |taction| Message::TimerAction(index, taction);
+        let minutes = match (&self.timer_input, timer.mode) {
+            (Some(input), _) => input.clone(),
+            (None, TimerMode::Until) => hours_minutes(timer.minutes),
+            (None, _) => timer.minutes.to_string(),
+        };
+        let mut setup = row![
+            pick_list(TimerMode::ALL, Some(timer.mode), move |mode| action(
+                TAction::Mode(mode)
+            ))
+            .text_size(self.set.font_size)
+            .style(style::theme_pick_list),
+        ]
+        .spacing(self.set.spacing);
+        if timer.mode != TimerMode::Clock {
+            setup = setup.push(
+                text_input(
+                    if timer.mode == TimerMode::Until {
+                        "HH:MM"
+                    } else {
+                        "Minutes"
+                    },
+                    &minutes,
+                )
+                .on_input(move |minutes| action(TAction::Minutes(minutes)))
+                .on_submit(action(TAction::ApplyMinutes))
+                .size(self.set.font_size),
+            );
+        }
+        let controls = row![
+            button(ttext("Start", self))
+                .on_press_maybe((!timer.is_running()).then(|| action(TAction::Start))),
+            button(ttext("Pause", self))
+                .on_press_maybe(timer.is_running().then(|| action(TAction::Pause))),
+            button(ttext("Reset", self))
+                .style(button::secondary)
+                .on_press(action(TAction::Reset)),
+        ]
+        .spacing(self.set.spacing);
+        column![
+            ttext(timer.text(self.clock()), self)
+                .size(self.set.font_size * 4.0)
+                .width(Length::Fill)
+                .center(),
+            setup,
+            controls,
+            toggler(timer.auto_advance)
+                .label("Go to the next item at zero")
+                .on_toggle(move |on| action(TAction::AutoAdvance(on)))
+                .size(self.set.font_size)
+                .text_size(self.set.font_size),
+            toggler(timer.overlay)
+                .label("Show over the next items")
+                .on_toggle(move |on| action(TAction::Overlay(on)))
+                .size(self.set.font_size)
+                .text_size(self.set.font_size),
+        ]
+        .spacing(self.set.spacing * 2.0)
+    }
+
+    // Running timer shown over the other items
+    pub fn overlay(&self) -> Option<String> {
+        let current = self.service.current_song_index()?;
+        self.service
+            .iter()
+            .enumerate()
+            .find_map(|(index, item)| match &item.kind {
+                ItemKind::Timer(timer)
+                    if index != current && timer.overlay && timer.is_running() =>
+                {
+                    Some(timer.text(self.clock()))
+                }
+                _ => None,
+            })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn timer_text() {
+        let mut timer = Timer::default();
+        // 9:59:30 and 10:01:05 for a countdown to 10:00
+        assert_eq!(timer.text(10 * 3600 - 30), "00:30");
+        assert_eq!(timer.text(10 * 3600 + 65), "+01:05");
+        timer.mode = TimerMode::Duration;
+        timer.minutes = 90;
+        assert_eq!(timer.text(0), "1:30:00");
+        timer.mode = TimerMode::Clock;
+        assert_eq!(timer.text(3661), "01:01:01");
+        let timer = Timer::from_spec("duration 5 advance").unwrap();
+        assert!(timer.auto_advance && !timer.overlay);
+        assert_eq!(timer.spec(), "duration 5 advance");
+        assert_eq!(parse_minutes("10:30"), Some(630));
+    }
+}
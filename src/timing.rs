@@ -0,0 +1,461 @@
+// Planned durations of the items, and the actual timings of the services
+use iced::{
+    Element, Length, Task,
+    widget::{Row, button, column, horizontal_space, pick_list, row, scrollable, text},
+};
+use rusqlite::{Connection, Result, params};
+use std::{fmt::Display, time::Instant};
+
+use crate::{App, Message, style, widget::ttext};
+
+// Service running since its first item was shown
+#[derive(Debug, Default)]
+pub struct Timing {
+    run: Option<i64>,
+    started: Option<Instant>,
+    // Current item and when it was shown
+    item: Option<(usize, Instant)>,
+    // Planned duration being typed
+    input: Option<(usize, String)>,
+}
+
+impl Timing {
+    // An item was inserted at this position of the service
+    pub fn shift(&mut self, index: usize) {
+        if let Some((item, _)) = &mut self.item
+            && *item >= index
+        {
+            *item += 1;
+        }
+        if let Some((item, _)) = &mut self.input
+            && *item >= index
+        {
+            *item += 1;
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Run {
+    id: i64,
+    service: String,
+    started_at: String,
+}
+
+impl Display for Run {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.started_at, self.service)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Line {
+    title: String,
+    planned: u32,
+    // Until the next item, unknown for the last one of a service not ended
+    actual: Option<i64>,
+}
+
+#[derive(Debug, Default)]
+pub struct TimingReport {
+    runs: Vec<Run>,
+    run: Option<Run>,
+    lines: Vec<Line>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RAction {
+    Select(Run),
+    Delete(i64),
+}
+
+// Seconds of "4:30", or of "5" minutes
+pub fn parse_duration(text: &str) -> Option<u32> {
+    match text.trim().split_once(':') {
+        Some((minutes, seconds)) => {
+            Some(minutes.trim().parse::<u32>().ok()? * 60 + seconds.trim().parse::<u32>().ok()?)
+        }
+        None => text.trim().parse::<u32>().ok().map(|minutes| minutes * 60),
+    }
+}
+
+pub fn format_duration(seconds: i64) -> String {
+    let sign = if seconds < 0 { "-" } else { "" };
+    let seconds = seconds.abs();
+    match seconds / 3600 {
+        0 => format!("{}{}:{:02}", sign, seconds / 60, seconds % 60),
+        hours => format!(
+            "{}{}:{:02}:{:02}",
+            sign,
+            hours,
+            seconds / 60 % 60,
+            seconds % 60
+        ),
+    }
+}
+
+// Ahead when negative: the latest of the start of the item and of its planned end
+fn lateness(planned: &[u32], current: usize, item_start: i64, now: i64) -> i64 {
+    // The list may have changed since the item went live
+    let before: i64 = planned[..current.min(planned.len())]
+        .iter()
+        .map(|&seconds| seconds as i64)
+        .sum();
+    let through = before + planned.get(current).copied().unwrap_or(0) as i64;
+    (item_start - before).max(now - through)
+}
+
+fn insert_run(db: &Connection, service: &str) -> Result<i64> {
+    db.execute(
+        "INSERT INTO service_runs (service, started_at)
+            VALUES (?1, datetime('now', 'localtime'));",
+        [service],
+    )?;
+    Ok(db.last_insert_rowid())
+}
+
+fn log_start(db: &Connection, run: i64, position: usize, title: &str, planned: u32) -> Result<()> {
+    db.execute(
+        "INSERT INTO service_log (run_id, position, title, planned, started_at)
+            VALUES (?1, ?2, ?3, ?4, datetime('now', 'localtime'));",
+        params![run, position, title, planned],
+    )?;
+    Ok(())
+}
+
+fn end_run(db: &Connection, run: i64) -> Result<()> {
+    db.execute(
+        "UPDATE service_runs SET ended_at = datetime('now', 'localtime') WHERE id = ?1;",
+        [run],
+    )?;
+    Ok(())
+}
+
+fn delete_run(db: &Connection, run: i64) -> Result<()> {
+    db.execute("DELETE FROM service_log WHERE run_id = ?1;", [run])?;
+    db.execute("DELETE FROM service_runs WHERE id = ?1;", [run])?;
+    Ok(())
+}
+
+fn load_runs(db: &Connection) -> Result<Vec<Run>> {
+    let mut query = db.prepare(
+        "SELECT id, COALESCE(service, ''), started_at FROM service_runs
+            ORDER BY started_at DESC, id DESC;",
+    )?;
+    query
+        .query_map([], |row| {
+            Ok(Run {
+                id: row.get(0)?,
+                service: row.get(1)?,
+                started_at: row.get(2)?,
+            })
+        })?
+        .collect()
+}
+
+fn load_lines(db: &Connection, run: i64) -> Result<Vec<Line>> {
+    let mut query = db.prepare(
+        "SELECT title, planned, CAST(strftime('%s', started_at) AS INTEGER) FROM service_log
+            WHERE run_id = ?1
+            ORDER BY rowid;",
+    )?;
+    let starts = query
+        .query_map([run], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, u32>(1)?,
+                row.get::<_, i64>(2)?,
+            ))
+        })?
+        .collect::<Result<Vec<_>>>()?;
+    let ended: Option<i64> = db.query_row(
+        "SELECT CAST(strftime('%s', ended_at) AS INTEGER) FROM service_runs WHERE id = ?1;",
+        [run],
+        |row| row.get(0),
+    )?;
+    Ok(starts
+        .iter()
+        .enumerate()
+        .map(|(index, (title, planned, start))| Line {
+            title: title.clone(),
+            planned: *planned,
+            actual: starts
+                .get(index + 1)
+                .map(|(_, _, next)| *next)
+                .or(ended)
+                .map(|end| end - start),
+        })
+        .collect())
+}
+
+impl App {
+    // Each time an item goes live, the first one starts the service
+    pub fn start_item(&mut self, index: usize) {
+        let now = Instant::now();
+        if self.timing.run.is_none() {
+            match insert_run(&self.db, &self.service.name) {
+                Ok(run) => {
+                    self.timing.run = Some(run);
+                    self.timing.started = Some(now);
+                }
+                Err(e) => println!("ERROR: Failed to start the service: {}", e),
+            }
+        }
+        self.timing.item = Some((index, now));
+        if let (Some(run), Some(item)) = (self.timing.run, self.service.iter().nth(index))
+            && let Err(e) = log_start(
+                &self.db,
+                run,
+                index,
+                &item.title(&self.books),
+                item.duration,
+            )
+        {
+            println!("ERROR: Failed to log the item: {}", e);
+        }
+    }
+
+    pub fn end_service(&mut self) {
+        if let Some(run) = self.timing.run
+            && let Err(e) = end_run(&self.db, run)
+        {
+            println!("ERROR: Failed to end the service: {}", e);
+        }
+        self.timing = Timing::default();
+    }
+
+    pub fn service_running(&self) -> bool {
+        self.timing.started.is_some()
+    }
+
+    pub fn duration_changed(&mut self, index: usize, duration: String) {
+        self.timing.input = Some((index, duration));
+    }
+
+    pub fn apply_duration(&mut self) {
+        if let Some((index, duration)) = self.timing.input.take() {
+            match duration.trim() {
+                "" => self.service.set_duration(index, 0),
+                duration => {
+                    if let Some(seconds) = parse_duration(duration) {
+                        self.service.set_duration(index, seconds);
+                    }
+                }
+            }
+        }
+    }
+
+    // Planned duration as typed or saved
+    pub fn duration_input(&self, index: usize, planned: u32) -> String {
+        match &self.timing.input {
+            Some((input, duration)) if *input == index => duration.clone(),
+            _ if planned == 0 => String::new(),
+            _ => format_duration(planned as i64),
+        }
+    }
+
+    // Elapsed and remaining time of the item, and how late is the service
+    pub fn view_clock(&self) -> Row<'_, Message> {
+        let report = button(ttext("Timings", self))
+            .style(button::secondary)
+            .on_press(Message::OpenTimings);
+        let (Some(started), Some((current, item_started))) =
+            (self.timing.started, self.timing.item)
+        else {
+            return row![
+                ttext("Not started", self).style(style::soft_text),
+                horizontal_space(),
+                report
+            ]
+            .spacing(self.set.spacing);
+        };
+        let planned: Vec<u32> = self.service.iter().map(|item| item.duration).collect();
+        let elapsed = item_started.elapsed().as_secs() as i64;
+        let mut clock = row![].spacing(self.set.spacing);
+        clock = match planned.get(current).copied().unwrap_or(0) as i64 {
+            0 => clock.push(ttext(format!("Item {}", format_duration(elapsed)), self)),
+            planned => clock.push(
+                ttext(
+                    format!(
+                        "Item {} / {}, {} left",
+                        format_duration(elapsed),
+                        format_duration(planned),
+                        format_duration(planned - elapsed)
+                    ),
+                    self,
+                )
+                .style(if elapsed > planned {
+                    text::danger
+                } else {
+                    text::default
+                }),
+            ),
+        };
+        clock = clock.push(ttext(
+            format!(
+                "Service {}",
+                format_duration(started.elapsed().as_secs() as i64)
+            ),
+            self,
+        ));
+        // Only with a plan to compare with
+        if planned.iter().any(|&seconds| seconds > 0) {
+            let late = lateness(
+                &planned,
+                current,
+                item_started.duration_since(started).as_secs() as i64,
+                started.elapsed().as_secs() as i64,
+            );
+            clock = clock.push(match late > 0 {
+                true => ttext(format!("{} late", format_duration(late)), self).style(text::danger),
+                false => {
+                    ttext(format!("{} ahead", format_duration(-late)), self).style(style::soft_text)
+                }
+            });
+        }
+        clock
+            .push(horizontal_space())
+            .push(
+                button(ttext("End", self))
+                    .style(button::danger)
+                    .on_press(Message::EndService),
+            )
+            .push(report)
+    }
+
+    pub fn open_timings(&mut self) -> Task<Message> {
+        self.timings.runs = load_runs(&self.db).expect("ERROR: Failed to load the services");
+        if let Some(run) = self.timings.runs.first().cloned() {
+            self.select_run(run);
+        }
+        crate::WId::open(&mut self.window.timings)
+    }
+
+    fn select_run(&mut self, run: Run) {
+        self.timings.lines =
+            load_lines(&self.db, run.id).expect("ERROR: Failed to load the timings");
+        self.timings.run = Some(run);
+    }
+
+    pub fn timings(&mut self, raction: RAction) {
+        match raction {
+            RAction::Select(run) => self.select_run(run),
+            RAction::Delete(run) => {
+                if let Err(e) = delete_run(&self.db, run) {
+                    println!("ERROR: Failed to delete the service: {}", e);
+                }
+                self.timings = TimingReport {
+                    runs: load_runs(&self.db).expect("ERROR: Failed to load the services"),
+                    ..Default::default()
+                };
+            }
+        }
+    }
+
+    // Planned against actual durations of a service
+    pub fn view_timings(&self) -> Element<'_, Message> {
+        let report = &self.timings;
+        let mut lines = column![
+            row![
+                ttext("Item", self).width(Length::FillPortion(5)),
+                ttext("Planned", self).width(Length::FillPortion(1)),
+                ttext("Actual", self).width(Length::FillPortion(1)),
+                ttext("Difference", self).width(Length::FillPortion(1)),
+            ]
+            .spacing(self.set.spacing)
+        ];
+        for line in &report.lines {
+            let difference = match (line.planned, line.actual) {
+                (0, _) | (_, None) => None,
+                (planned, Some(actual)) => Some(actual - planned as i64),
+            };
+            lines = lines.push(
+                row![
+                    ttext(line.title.as_str(), self).width(Length::FillPortion(5)),
+                    ttext(
+                        match line.planned {
+                            0 => String::new(),
+                            planned => format_duration(planned as i64),
+                        },
+                        self
+                    )
+                    .style(style::soft_text)
+                    .width(Length::FillPortion(1)),
+                    ttext(line.actual.map(format_duration).unwrap_or_default(), self)
+                        .width(Length::FillPortion(1)),
+                    ttext(
+                        difference
+                            .map(|difference| format!("{:+}s", difference))
+                            .unwrap_or_default(),
+                        self
+                    )
+                    .style(if difference.is_some_and(|difference| difference > 0) {
+                        text::danger
+                    } else {
+                        text::default
+                    })
+                    .width(Length::FillPortion(1)),
+                ]
+                .spacing(self.set.spacing),
+            );
+        }
+        let planned: i64 = report.lines.iter().map(|line| line.planned as i64).sum();
+        let actual: i64 = report.lines.iter().filter_map(|line| line.actual).sum();
+        column![
+            row![
+                pick_list(report.runs.as_slice(), report.run.as_ref(), |run| {
+                    Message::TimingAction(RAction::Select(run))
+                })
+                .placeholder("No service logged")
+                .text_size(self.set.font_size)
+                .style(style::theme_pick_list)
+                .width(Length::Fill),
+                button(ttext("Delete", self))
+                    .style(button::danger)
+                    .on_press_maybe(
+                        report
+                            .run
+                            .as_ref()
+                            .map(|run| Message::TimingAction(RAction::Delete(run.id)))
+                    ),
+            ]
+            .spacing(self.set.spacing),
+            scrollable(lines.spacing(self.set.spacing)).height(Length::Fill),
+            row![
+                horizontal_space(),
+                ttext(
+                    format!(
+                        "Planned {}, actual {}",
+                        format_duration(planned),
+                        format_duration(actual)
+                    ),
+                    self
+                )
+            ],
+        ]
+        .spacing(self.set.spacing)
+        .padding(5)
+        .into()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn service_lateness() {
+        assert_eq!(parse_duration("4:30"), Some(270));
+        assert_eq!(parse_duration("5"), Some(300));
+        assert_eq!(format_duration(-75), "-1:15");
+        // Items of 5, 10 and 5 minutes, the second one shown after 6 minutes
+        let planned = [300, 600, 300];
+        assert_eq!(lateness(&planned, 1, 360, 400), 60);
+        // Still on it after 17 minutes
+        assert_eq!(lateness(&planned, 1, 360, 1020), 120);
+        // Ahead: the third one shown after 14 minutes
+        assert_eq!(lateness(&planned, 2, 840, 850), -60);
+        // The current item is past a shorter list
+        assert_eq!(lateness(&planned[..1], 2, 840, 850), 550);
+    }
+}
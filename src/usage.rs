@@ -0,0 +1,170 @@
+// Songs projected over a period, for the CCLI reports
+use iced::{
+    Element, Length, Task,
+    widget::{Column, button, column, horizontal_space, row, scrollable, text_input},
+};
+use rusqlite::{Connection, Result};
+use std::path::Path;
+
+use crate::{App, Message, style, widget::ttext};
+
+#[derive(Debug, Default)]
+pub struct UsageReport {
+    from: String,
+    to: String,
+    lines: Vec<Line>,
+}
+
+#[derive(Debug, Clone)]
+struct Line {
+    title: String,
+    ccli_number: Option<String>,
+    authors: String,
+    count: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UAction {
+    From(String),
+    To(String),
+    Export,
+}
+
+// Number of uses of each song between two dates (YYYY-MM-DD)
+fn load_usage(db: &Connection, from: &str, to: &str) -> Result<Vec<Line>> {
+    let mut query = db.prepare(
+        "SELECT s.title, s.ccli_number, COUNT(u.id),
+                (SELECT GROUP_CONCAT(a.name, ', ') FROM authors a
+                    JOIN authors_songs asng ON a.id = asng.author_id
+                    WHERE asng.song_id = s.id)
+            FROM song_usage u
+            JOIN songs s ON s.id = u.song_id
+            WHERE date(u.used_at) BETWEEN ?1 AND ?2
+            GROUP BY s.id
+            ORDER BY COUNT(u.id) DESC, s.title;",
+    )?;
+    query
+        .query_map([from, to], |row| {
+            Ok(Line {
+                title: row.get(0)?,
+                ccli_number: row.get(1)?,
+                count: row.get(2)?,
+                authors: row.get::<_, Option<String>>(3)?.unwrap_or_default(),
+            })
+        })?
+        .collect()
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn write_csv(lines: &[Line], path: &Path) -> std::io::Result<()> {
+    let mut csv = String::from("Title,CCLI Number,Authors,Uses\n");
+    for line in lines {
+        csv += &format!(
+            "{},{},{},{}\n",
+            csv_field(&line.title),
+            csv_field(line.ccli_number.as_deref().unwrap_or_default()),
+            csv_field(&line.authors),
+            line.count
+        );
+    }
+    std::fs::write(path, csv)
+}
+
+impl App {
+    pub fn open_usage(&mut self) -> Task<Message> {
+        if self.usage.from.is_empty() {
+            // The last six months by default
+            let dates = self.db.query_row(
+                "SELECT date('now', 'localtime', '-6 months'), date('now', 'localtime');",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            );
+            if let Ok((from, to)) = dates {
+                (self.usage.from, self.usage.to) = (from, to);
+            }
+        }
+        self.reload_usage();
+        crate::WId::open(&mut self.window.usage)
+    }
+
+    fn reload_usage(&mut self) {
+        self.usage.lines = load_usage(&self.db, &self.usage.from, &self.usage.to)
+            .expect("ERROR: Failed to load the usage");
+    }
+
+    pub fn usage(&mut self, uaction: UAction) -> Task<Message> {
+        match uaction {
+            UAction::From(from) => self.usage.from = from,
+            UAction::To(to) => self.usage.to = to,
+            UAction::Export => {
+                let name = format!("CCLI {} {}.csv", self.usage.from, self.usage.to);
+                return Task::perform(
+                    rfd::AsyncFileDialog::new()
+                        .set_file_name(name)
+                        .add_filter("CSV", &["csv"])
+                        .save_file(),
+                    |file| file.map(|file| Message::ExportUsage(file.path().to_path_buf())),
+                )
+                .and_then(Task::done);
+            }
+        }
+        self.reload_usage();
+        Task::none()
+    }
+
+    pub fn export_usage(&self, path: &Path) {
+        if let Err(e) = write_csv(&self.usage.lines, path) {
+            println!("ERROR: Failed to export the usage: {}", e);
+        }
+    }
+
+    pub fn view_usage(&self) -> Element<'_, Message> {
+        let usage = &self.usage;
+        let mut lines = Column::with_capacity(usage.lines.len());
+        for line in &usage.lines {
+            lines = lines.push(
+                row![
+                    ttext(&line.title, self).width(Length::FillPortion(4)),
+                    ttext(line.ccli_number.as_deref().unwrap_or_default(), self)
+                        .style(style::soft_text)
+                        .width(Length::FillPortion(2)),
+                    ttext(&line.authors, self)
+                        .style(style::soft_text)
+                        .width(Length::FillPortion(4)),
+                    ttext(line.count.to_string(), self).width(Length::FillPortion(1)),
+                ]
+                .spacing(self.set.spacing),
+            );
+        }
+        let total: usize = usage.lines.iter().map(|line| line.count).sum();
+        column![
+            row![
+                ttext("From", self),
+                text_input("YYYY-MM-DD", &usage.from)
+                    .on_input(|from| Message::UsageAction(UAction::From(from)))
+                    .size(self.set.font_size),
+                ttext("To", self),
+                text_input("YYYY-MM-DD", &usage.to)
+                    .on_input(|to| Message::UsageAction(UAction::To(to)))
+                    .size(self.set.font_size),
+                button(ttext("Export CSV", self)).on_press(Message::UsageAction(UAction::Export)),
+            ]
+            .spacing(self.set.spacing),
+            scrollable(lines).height(Length::Fill),
+            row![
+                horizontal_space(),
+                ttext(format!("{} songs, {} uses", usage.lines.len(), total), self)
+            ],
+        ]
+        .spacing(self.set.spacing)
+        .padding(5)
+        .into()
+    }
+}